@@ -1,29 +1,84 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{char, line_ending, multispace0, space0, space1, u32},
+    combinator::{all_consuming, cut, value},
+    error::{context, ErrorKind, VerboseError, VerboseErrorKind},
+    multi::separated_list1,
+    sequence::{delimited, tuple},
+    IResult,
+};
+use std::fmt;
 use std::iter::Sum;
 use std::ops::AddAssign;
 use std::str::FromStr;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Blue => "blue",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/* cubes exactly as they were listed, a colour may appear more than once */
+#[derive(Debug, Default, Clone)]
 struct GameSet {
-    red: u32,
-    green: u32,
-    blue: u32,
+    entries: Vec<(u32, Color)>,
 }
 
+impl GameSet {
+    fn count(&self, color: Color) -> u32 {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| *entry == color)
+            .map(|(num, _)| num)
+            .sum()
+    }
+
+    fn red(&self) -> u32 {
+        self.count(Color::Red)
+    }
+
+    fn green(&self) -> u32 {
+        self.count(Color::Green)
+    }
+
+    fn blue(&self) -> u32 {
+        self.count(Color::Blue)
+    }
+}
+
+/* sets are equal when they hold the same cubes, however they were listed */
+impl PartialEq for GameSet {
+    fn eq(&self, other: &Self) -> bool {
+        [Color::Red, Color::Green, Color::Blue]
+            .iter()
+            .all(|color| self.count(*color) == other.count(*color))
+    }
+}
+
+impl Eq for GameSet {}
+
 impl Sum for GameSet {
     fn sum<I>(iter: I) -> Self
     where
         I: Iterator<Item = GameSet>,
     {
-        let mut total = GameSet {
-            red: 0,
-            green: 0,
-            blue: 0,
-        };
+        let mut total = GameSet::default();
 
         for set in iter {
-            total.red += set.red;
-            total.green += set.green;
-            total.blue += set.blue;
+            total += set;
         }
 
         total
@@ -32,11 +87,7 @@ impl Sum for GameSet {
 
 impl AddAssign for GameSet {
     fn add_assign(&mut self, other: Self) {
-        *self = Self {
-            red: self.red + other.red,
-            green: self.green + other.green,
-            blue: self.blue + other.blue,
-        };
+        self.entries.extend(other.entries);
     }
 }
 
@@ -44,77 +95,168 @@ impl FromStr for GameSet {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<GameSet, Self::Err> {
-        let set = s
-            .split(", ")
-            .map(|value| {
-                let (num, color) = value.split_once(' ').unwrap();
-                let num = num.parse::<u32>().unwrap();
-
-                if color == "red" {
-                    GameSet {
-                        red: num,
-                        green: 0,
-                        blue: 0,
-                    }
-                } else if color == "green" {
-                    GameSet {
-                        red: 0,
-                        green: num,
-                        blue: 0,
-                    }
-                } else if color == "blue" {
-                    GameSet {
-                        red: 0,
-                        green: 0,
-                        blue: num,
-                    }
-                } else {
-                    panic!("Error parsing colors");
-                }
-            })
-            .sum::<GameSet>();
+        let (_, set) = all_consuming(delimited(space0, parse_set, space0))(s)
+            .map_err(|err| ParseError::from_nom(s, err))?;
 
         Ok(set)
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for GameSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (idx, (num, color)) in self.entries.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{num} {color}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Game {
     id: u32,
     sets: Vec<GameSet>,
 }
 
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Game {}: ", self.id)?;
+        for (idx, set) in self.sets.iter().enumerate() {
+            if idx > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{set}")?;
+        }
+        Ok(())
+    }
+}
+
 impl FromStr for Game {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Game, Self::Err> {
-        let (part_game_id, part_sets) = s.split_once(": ").unwrap();
+        let (_, game) = all_consuming(parse_game)(s).map_err(|err| ParseError::from_nom(s, err))?;
 
-        let id = part_game_id
-            .get(
-                part_game_id
-                    .find(char::is_numeric)
-                    .expect("Could not find game id")..,
-            )
-            .expect("Could not get game id")
-            .parse::<u32>()?;
+        Ok(game)
+    }
+}
 
-        let sets: Result<Vec<GameSet>, anyhow::Error> =
-            part_sets.split("; ").map(GameSet::from_str).collect();
-        let sets = sets?;
+#[derive(Debug, PartialEq, Eq)]
+struct ParseError {
+    offset: usize,
+    expected: String,
+}
 
-        Ok(Game { id, sets })
+impl ParseError {
+    fn from_nom(input: &str, err: nom::Err<VerboseError<&str>>) -> ParseError {
+        let errors = match err {
+            nom::Err::Error(err) | nom::Err::Failure(err) => err.errors,
+            nom::Err::Incomplete(_) => {
+                return ParseError {
+                    offset: input.len(),
+                    expected: "more input".to_string(),
+                }
+            }
+        };
+
+        /* innermost error gives the position, first named token gives the expectation */
+        let offset = errors
+            .first()
+            .map(|(rest, _)| input.len() - rest.len())
+            .unwrap_or(0);
+        let expected = errors
+            .iter()
+            .find_map(|(_, kind)| match kind {
+                VerboseErrorKind::Context(ctx) => Some(ctx.to_string()),
+                VerboseErrorKind::Char(ch) => Some(format!("'{ch}'")),
+                VerboseErrorKind::Nom(ErrorKind::Eof) => Some("end of line".to_string()),
+                VerboseErrorKind::Nom(_) => None,
+            })
+            .unwrap_or_else(|| "valid input".to_string());
+
+        ParseError { offset, expected }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} at byte {}", self.expected, self.offset)
     }
 }
 
+impl std::error::Error for ParseError {}
+
+fn parse_color(input: &str) -> IResult<&str, Color, VerboseError<&str>> {
+    context(
+        "colour",
+        alt((
+            value(Color::Red, tag("red")),
+            value(Color::Green, tag("green")),
+            value(Color::Blue, tag("blue")),
+        )),
+    )(input)
+}
+
+fn parse_cubes(input: &str) -> IResult<&str, GameSet, VerboseError<&str>> {
+    let (input, (num, _, color)) = tuple((
+        context("number", u32),
+        context("space", space1),
+        parse_color,
+    ))(input)?;
+
+    Ok((
+        input,
+        GameSet {
+            entries: vec![(num, color)],
+        },
+    ))
+}
+
+fn parse_set(input: &str) -> IResult<&str, GameSet, VerboseError<&str>> {
+    let (input, cubes) =
+        separated_list1(tuple((space0, char(','), space0)), cut(parse_cubes))(input)?;
+
+    Ok((input, cubes.into_iter().sum()))
+}
+
+fn parse_game(input: &str) -> IResult<&str, Game, VerboseError<&str>> {
+    /* once the "Game" keyword matched, any later mismatch is reported as-is */
+    let (input, (_, _, (_, id, _, _, _, sets, _))) = tuple((
+        space0,
+        context("\"Game\"", tag("Game")),
+        cut(tuple((
+            context("space", space1),
+            context("game id", u32),
+            space0,
+            char(':'),
+            space0,
+            separated_list1(tuple((space0, char(';'), space0)), parse_set),
+            space0,
+        ))),
+    ))(input)?;
+
+    Ok((input, Game { id, sets }))
+}
+
+fn parse_games(input: &str) -> Result<Vec<Game>, ParseError> {
+    let (_, games) = all_consuming(delimited(
+        multispace0,
+        separated_list1(line_ending, parse_game),
+        multispace0,
+    ))(input)
+    .map_err(|err| ParseError::from_nom(input, err))?;
+
+    Ok(games)
+}
+
 fn part1(input: &str) -> u32 {
     let max_possible_game = GameSet {
-        red: 12,
-        green: 13,
-        blue: 14,
+        entries: vec![(12, Color::Red), (13, Color::Green), (14, Color::Blue)],
     };
 
-    let games: Result<Vec<Game>, anyhow::Error> = input.lines().map(Game::from_str).collect();
+    let games = parse_games(input);
 
     let mut total = 0;
 
@@ -122,9 +264,9 @@ fn part1(input: &str) -> u32 {
         let mut possible = true;
 
         for game_set in game.sets {
-            if game_set.red > max_possible_game.red
-                || game_set.green > max_possible_game.green
-                || game_set.blue > max_possible_game.blue
+            if game_set.red() > max_possible_game.red()
+                || game_set.green() > max_possible_game.green()
+                || game_set.blue() > max_possible_game.blue()
             {
                 possible = false;
                 break;
@@ -140,7 +282,7 @@ fn part1(input: &str) -> u32 {
 }
 
 fn part2(input: &str) -> u32 {
-    let games: Result<Vec<Game>, anyhow::Error> = input.lines().map(Game::from_str).collect();
+    let games = parse_games(input);
 
     let mut total = 0;
 
@@ -149,12 +291,14 @@ fn part2(input: &str) -> u32 {
             .sets
             .iter()
             .fold(GameSet::default(), |acc, game_set| GameSet {
-                red: acc.red.max(game_set.red),
-                green: acc.green.max(game_set.green),
-                blue: acc.blue.max(game_set.blue),
+                entries: vec![
+                    (acc.red().max(game_set.red()), Color::Red),
+                    (acc.green().max(game_set.green()), Color::Green),
+                    (acc.blue().max(game_set.blue()), Color::Blue),
+                ],
             });
 
-        total += max_game.red * max_game.green * max_game.blue;
+        total += max_game.red() * max_game.green() * max_game.blue();
     }
 
     total
@@ -163,10 +307,10 @@ fn part2(input: &str) -> u32 {
 fn main() {
     let input = include_str!("../input.txt");
 
-    //let result = part1(input);
+    let result = part1(input);
+    println!("Result part 1: {result}");
     let result = part2(input);
-
-    println!("Result: {result}");
+    println!("Result part 2: {result}");
 }
 
 #[test]
@@ -176,7 +320,7 @@ fn test_part1() {
     Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
     Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
     Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
-    assert_eq!(part1(&input), 8);
+    assert_eq!(part1(input), 8);
 }
 
 #[test]
@@ -186,5 +330,62 @@ fn test_part2() {
     Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
     Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
     Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
-    assert_eq!(part2(&input), 2286);
+    assert_eq!(part2(input), 2286);
+}
+
+#[test]
+fn test_display_round_trip() {
+    let input = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 red, 2 red; 3 blue, 1 green, 4 blue";
+
+    for line in input.lines() {
+        let game = Game::from_str(line).unwrap();
+        assert_eq!(game.to_string(), line);
+        assert_eq!(Game::from_str(&game.to_string()).unwrap(), game);
+    }
+
+    /* repeated colours still count towards the totals */
+    let game = Game::from_str("Game 4: 1 red, 2 red; 3 blue, 1 green, 4 blue").unwrap();
+    assert_eq!((game.sets[0].red(), game.sets[1].blue()), (3, 7));
+}
+
+#[test]
+fn test_game_set_by_hand() {
+    let set = GameSet {
+        entries: vec![(2, Color::Green), (5, Color::Red)],
+    };
+    assert_eq!(set.to_string(), "2 green, 5 red");
+    assert_eq!((set.red(), set.green(), set.blue()), (5, 2, 0));
+
+    /* the order cubes were listed in does not matter for equality */
+    let listed = GameSet::from_str("5 red, 1 green, 1 green").unwrap();
+    assert_eq!(listed, set);
+    assert_ne!(listed.to_string(), set.to_string());
+}
+
+#[test]
+fn test_parse_flexible_whitespace() {
+    let game = Game::from_str("  Game 7 :3 blue ,4 red;  1 red,\t2 green  ").unwrap();
+    assert_eq!(game.to_string(), "Game 7: 3 blue, 4 red; 1 red, 2 green");
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = parse_games("Game 1 3 blue").unwrap_err();
+    assert_eq!(
+        err,
+        ParseError {
+            offset: 7,
+            expected: "':'".to_string()
+        }
+    );
+
+    let err = parse_games("Game 1: 3 blue\nGame 2: 4 purple").unwrap_err();
+    assert_eq!(err.offset, 25);
+    assert_eq!(err.expected, "colour");
+
+    let err = parse_games("Game x: 3 blue").unwrap_err();
+    assert_eq!(err.to_string(), "expected game id at byte 5");
 }
@@ -1,4 +1,8 @@
+mod render;
+mod scan;
+
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 enum Point {
@@ -7,152 +11,451 @@ enum Point {
     Symbol(char),
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct EnginePart {
-    number: u32,
-    symbols_adjacent: HashMap<(usize, usize), char>,
-}
-
-fn parse(input: &str) -> Vec<EnginePart> {
-    let mut engine_parts: Vec<EnginePart> = Vec::new();
-    let mut game: BTreeMap<(usize, usize), Point> = BTreeMap::new();
-    let game_max_x = input.lines().count();
-    let game_max_y = input.lines().next().unwrap().chars().count();
-
-    let _: Vec<_> = input
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            let _: Vec<_> = line
-                .chars()
-                .enumerate()
-                .map(|(x, ch)| {
-                    let point = match ch {
-                        '.' => Point::Empty,
-                        part if ch.is_ascii_digit() => {
-                            Point::Part(part.to_digit(10).expect("Not a number"))
-                        }
-                        _ => Point::Symbol(ch),
-                    };
-
-                    game.insert((y, x), point);
-                })
-                .collect();
-        })
-        .collect();
+    number: u64,
+    row: usize,
+    cols: Range<usize>,
+}
 
-    let mut part_number = 0;
-    let mut symbols_adjacent: HashMap<(usize, usize), char> = HashMap::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Symbol {
+    ch: char,
+    row: usize,
+    col: usize,
+}
 
-    let _: Vec<_> = game
-        .iter()
-        .map(|((y, x), point)| {
-            if *x == 0 && *y != 0 {
-                /* start new line */
-                if part_number != 0 {
-                    engine_parts.push(EnginePart {
-                        number: part_number,
-                        symbols_adjacent: symbols_adjacent.clone(),
-                    });
-                }
-                symbols_adjacent.clear();
-                part_number = 0;
-            }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
 
-            if let Point::Part(num) = *point {
-                part_number = part_number * 10 + num;
-
-                /* find if it touches a symbol */
-                let neighbours: Vec<(i32, i32)> = vec![
-                    (1, 0),
-                    (-1, 0),
-                    (0, 1),
-                    (0, -1),
-                    (1, 1),
-                    (-1, -1),
-                    (1, -1),
-                    (-1, 1),
-                ];
-
-                for (nx, ny) in neighbours.iter() {
-                    let nx = nx + *x as i32;
-                    let ny = ny + *y as i32;
-
-                    if nx < 0 || ny < 0 {
-                        continue;
-                    }
-                    if nx > (game_max_x - 1) as i32 || ny > (game_max_y - 1) as i32 {
-                        continue;
-                    }
-                    let n = game.get(&(ny as usize, nx as usize)).unwrap();
-                    if let Point::Symbol(sym) = n {
-                        symbols_adjacent.insert((nx as usize, ny as usize), *sym);
+impl PartCount {
+    fn matches(&self, count: usize) -> bool {
+        match self {
+            PartCount::Exactly(n) => count == *n,
+            PartCount::AtLeast(n) => count >= *n,
+        }
+    }
+}
+
+/* Which symbols count as gears: symbol character (None for any symbol) and the
+ * number of adjacent parts required.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GearRule {
+    symbol: Option<char>,
+    parts: PartCount,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule {
+            symbol: Some('*'),
+            parts: PartCount::Exactly(2),
+        }
+    }
+}
+
+/* Cells considered adjacent to a part: orthogonal neighbours only, all eight
+ * neighbours, or everything within a Chebyshev distance.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Neighbourhood {
+    Four,
+    #[default]
+    Eight,
+    Radius(usize),
+}
+
+impl Neighbourhood {
+    fn cells(&self, part: &EnginePart) -> Vec<(usize, usize)> {
+        match self {
+            Neighbourhood::Four => {
+                let mut cells = Vec::new();
+                for col in part.cols.clone() {
+                    if part.row > 0 {
+                        cells.push((part.row - 1, col));
                     }
+                    cells.push((part.row + 1, col));
                 }
-            } else {
-                /* start a new part */
-                if part_number != 0 {
-                    engine_parts.push(EnginePart {
-                        number: part_number,
-                        symbols_adjacent: symbols_adjacent.clone(),
-                    });
+                if part.cols.start > 0 {
+                    cells.push((part.row, part.cols.start - 1));
                 }
-                symbols_adjacent.clear();
-                part_number = 0;
+                cells.push((part.row, part.cols.end));
+                cells
             }
+            Neighbourhood::Eight => Neighbourhood::Radius(1).cells(part),
+            Neighbourhood::Radius(radius) => {
+                let rows = part.row.saturating_sub(*radius)..=part.row + radius;
+                let cols = part.cols.start.saturating_sub(*radius)..part.cols.end + radius;
+
+                rows.flat_map(|row| cols.clone().map(move |col| (row, col)))
+                    .filter(|(row, col)| *row != part.row || !part.cols.contains(col))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct SymbolStats {
+    symbols: usize,
+    parts: usize,
+    sum: u64,
+}
+
+/* Engine schematic: dense grid of points plus the numbers and symbols found in
+ * it, connected by a bipartite adjacency graph (part <-> symbol).
+ */
+#[derive(Debug, Default)]
+struct Schematic {
+    width: usize,
+    height: usize,
+    grid: Vec<Point>,
+    neighbourhood: Neighbourhood,
+    parts: Vec<EnginePart>,
+    symbols: Vec<Symbol>,
+    symbol_index: HashMap<(usize, usize), usize>,
+    part_symbols: Vec<Vec<usize>>,
+    symbol_parts: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    fn get(&self, row: usize, col: usize) -> Option<&Point> {
+        if row < self.height && col < self.width {
+            self.grid.get(row * self.width + col)
+        } else {
+            None
+        }
+    }
+
+    fn symbols_of(&self, part: usize) -> impl Iterator<Item = &Symbol> {
+        self.part_symbols[part]
+            .iter()
+            .map(|idx| &self.symbols[*idx])
+    }
+
+    fn parts_of(&self, symbol: usize) -> impl Iterator<Item = &EnginePart> {
+        self.symbol_parts[symbol]
+            .iter()
+            .map(|idx| &self.parts[*idx])
+    }
+
+    fn is_counted(&self, part: usize) -> bool {
+        self.symbols_of(part).next().is_some()
+    }
+
+    fn gears<'a>(&'a self, rule: &'a GearRule) -> impl Iterator<Item = usize> + 'a {
+        (0..self.symbols.len()).filter(|idx| {
+            rule.symbol.is_none_or(|ch| self.symbols[*idx].ch == ch)
+                && rule.parts.matches(self.symbol_parts[*idx].len())
         })
-        .collect();
+    }
+
+    fn gear_ratio(&self, symbol: usize) -> u64 {
+        self.parts_of(symbol).map(|part| part.number).product()
+    }
+
+    fn part_sum(&self) -> u64 {
+        self.parts
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.is_counted(*idx))
+            .map(|(_, part)| part.number)
+            .sum()
+    }
+
+    fn gear_ratio_sum(&self, rule: &GearRule) -> u64 {
+        self.gears(rule).map(|symbol| self.gear_ratio(symbol)).sum()
+    }
+
+    fn parts_at(&self, row: usize, col: usize) -> Option<Vec<&EnginePart>> {
+        let symbol = self.symbol_index.get(&(row, col))?;
+
+        Some(self.parts_of(*symbol).collect())
+    }
+
+    /* parts grouped by the characters of the symbols they touch; a part touching
+     * several symbols of the same character is listed once for it
+     */
+    fn parts_by_symbol(&self) -> BTreeMap<char, Vec<&EnginePart>> {
+        let mut groups: BTreeMap<char, Vec<&EnginePart>> = BTreeMap::new();
+
+        for (idx, part) in self.parts.iter().enumerate() {
+            let mut chars: Vec<char> = self.symbols_of(idx).map(|sym| sym.ch).collect();
+            chars.sort();
+            chars.dedup();
+
+            for ch in chars {
+                groups.entry(ch).or_default().push(part);
+            }
+        }
+
+        groups
+    }
+
+    fn symbol_stats(&self) -> BTreeMap<char, SymbolStats> {
+        let mut stats: BTreeMap<char, SymbolStats> = BTreeMap::new();
+
+        for symbol in self.symbols.iter() {
+            stats.entry(symbol.ch).or_default().symbols += 1;
+        }
+        for (ch, parts) in self.parts_by_symbol() {
+            let entry = stats.entry(ch).or_default();
+            entry.parts = parts.len();
+            entry.sum = parts.iter().map(|part| part.number).sum();
+        }
 
-    engine_parts
+        stats
+    }
+
+    fn connect(&mut self) {
+        self.part_symbols = vec![Vec::new(); self.parts.len()];
+        self.symbol_parts = vec![Vec::new(); self.symbols.len()];
+
+        for (part_idx, part) in self.parts.iter().enumerate() {
+            for (row, col) in self.neighbourhood.cells(part) {
+                if let Some(Point::Symbol(_)) = self.get(row, col) {
+                    let symbol_idx = self.symbol_index[&(row, col)];
+                    self.part_symbols[part_idx].push(symbol_idx);
+                    self.symbol_parts[symbol_idx].push(part_idx);
+                }
+            }
+        }
+    }
 }
 
-fn part1(input: &str) -> u32 {
-    let engine_parts = parse(input);
+#[cfg(test)]
+fn parse(input: &str) -> Schematic {
+    parse_with(input, Neighbourhood::default())
+}
 
-    let total = engine_parts
+fn parse_with(input: &str, neighbourhood: Neighbourhood) -> Schematic {
+    let lines: Vec<&str> = input.lines().collect();
+    let height = lines.len();
+    let width = lines
         .iter()
-        .filter(|part| !part.symbols_adjacent.is_empty())
-        .map(|part| part.number)
-        .sum();
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut schematic = Schematic {
+        width,
+        height,
+        grid: vec![Point::Empty; width * height],
+        neighbourhood,
+        ..Default::default()
+    };
+
+    for (row, line) in lines.iter().enumerate() {
+        let mut number: Option<(u64, usize)> = None;
+
+        for (col, ch) in line.chars().enumerate() {
+            let point = match ch {
+                '.' => Point::Empty,
+                digit if ch.is_ascii_digit() => {
+                    Point::Part(digit.to_digit(10).expect("Not a number"))
+                }
+                _ => Point::Symbol(ch),
+            };
 
-    total
+            if let Point::Part(digit) = point {
+                let (value, start) = number.unwrap_or((0, col));
+                number = Some((value * 10 + digit as u64, start));
+            } else if let Some((value, start)) = number.take() {
+                schematic.parts.push(EnginePart {
+                    number: value,
+                    row,
+                    cols: start..col,
+                });
+            }
+
+            if let Point::Symbol(ch) = point {
+                schematic
+                    .symbol_index
+                    .insert((row, col), schematic.symbols.len());
+                schematic.symbols.push(Symbol { ch, row, col });
+            }
+
+            schematic.grid[row * width + col] = point;
+        }
+
+        /* number running up to the end of the line */
+        if let Some((value, start)) = number {
+            schematic.parts.push(EnginePart {
+                number: value,
+                row,
+                cols: start..line.chars().count(),
+            });
+        }
+    }
+
+    schematic.connect();
+
+    schematic
 }
 
-fn part2(input: &str) -> u32 {
-    let engine_parts = parse(input);
+#[cfg(test)]
+fn part1(input: &str) -> u64 {
+    parse(input).part_sum()
+}
 
-    let engine_parts_with_star_symbol: Vec<_> = engine_parts
-        .iter()
-        .filter(|part| part.symbols_adjacent.values().any(|sym| *sym == '*'))
-        .collect();
+#[cfg(test)]
+fn part2(input: &str) -> u64 {
+    parse(input).gear_ratio_sum(&GearRule::default())
+}
 
-    engine_parts_with_star_symbol
-        .iter()
-        .enumerate()
-        .map(|(idx, part1)| {
-            let sym1 = part1.symbols_adjacent.iter().find(|sym| *sym.1 == '*');
-
-            if let Some(sym1) = sym1 {
-                if let Some(part2) = engine_parts_with_star_symbol
-                    .iter()
-                    .skip(idx + 1)
-                    .find(|part| part.symbols_adjacent.iter().any(|sym| sym == sym1))
-                {
-                    return part1.number * part2.number;
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Render {
+    Ansi,
+    Html(String),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Options {
+    rule: GearRule,
+    neighbourhood: Neighbourhood,
+    render: Option<Render>,
+    stats: bool,
+    queries: Vec<(usize, usize)>,
+    stream: Option<String>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--gear-symbol" => {
+                let symbol = args.next().expect("Missing gear symbol");
+                options.rule.symbol = match symbol.as_str() {
+                    "any" => None,
+                    _ => Some(symbol.chars().next().expect("Empty gear symbol")),
                 };
-            };
+            }
+            "--gear-parts" => {
+                let count = args
+                    .next()
+                    .expect("Missing gear part count")
+                    .parse::<usize>()
+                    .expect("Gear part count must be a number");
+                options.rule.parts = match options.rule.parts {
+                    PartCount::Exactly(_) => PartCount::Exactly(count),
+                    PartCount::AtLeast(_) => PartCount::AtLeast(count),
+                };
+            }
+            "--at-least" => {
+                let (PartCount::Exactly(count) | PartCount::AtLeast(count)) = options.rule.parts;
+                options.rule.parts = PartCount::AtLeast(count);
+            }
+            "--neighbourhood" => {
+                let value = args.next().expect("Missing neighbourhood");
+                options.neighbourhood = match value.as_str() {
+                    "4" => Neighbourhood::Four,
+                    "8" => Neighbourhood::Eight,
+                    _ => Neighbourhood::Radius(
+                        value
+                            .strip_prefix('r')
+                            .and_then(|radius| radius.parse::<usize>().ok())
+                            .expect("Neighbourhood must be one of: 4, 8, r<N>"),
+                    ),
+                };
+            }
+            "--stats" => options.stats = true,
+            "--query" => {
+                let value = args.next().expect("Missing query position");
+                let (row, col) = value
+                    .split_once(',')
+                    .and_then(|(row, col)| Some((row.parse().ok()?, col.parse().ok()?)))
+                    .expect("Query position must be <row>,<col>");
+                options.queries.push((row, col));
+            }
+            "--stream" => {
+                options.stream = Some(args.next().expect("Missing file to stream, or - for stdin"));
+            }
+            "--render" => {
+                options.render = match args.next().as_deref() {
+                    Some("ansi") => Some(Render::Ansi),
+                    Some("html") => Some(Render::Html(
+                        args.next().expect("Missing output file for html render"),
+                    )),
+                    _ => panic!("Render mode must be one of: ansi, html <file>"),
+                };
+            }
+            _ => panic!("Unknown argument: {arg}"),
+        }
+    }
 
-            0
-        })
-        .sum()
+    /* the streaming scanner only keeps three rows and checks the eight cells around */
+    if options.stream.is_some()
+        && !matches!(
+            options.neighbourhood,
+            Neighbourhood::Eight | Neighbourhood::Radius(1)
+        )
+    {
+        panic!("--stream only supports --neighbourhood 8");
+    }
+
+    options
 }
 
 fn main() {
     let input = include_str!("../input.txt");
+    let options = parse_args(std::env::args().skip(1));
+
+    if let Some(path) = &options.stream {
+        let totals = if path == "-" {
+            scan::scan(std::io::stdin().lock(), &options.rule)
+        } else {
+            let file = std::fs::File::open(path).expect("Unable to open schematic");
+            scan::scan(std::io::BufReader::new(file), &options.rule)
+        }
+        .expect("Unable to read schematic");
+
+        println!("Result part 1: {}", totals.part_sum);
+        println!("Result part 2: {}", totals.gear_ratio_sum);
+        return;
+    }
 
-    let result = part1(input);
+    let schematic = parse_with(input, options.neighbourhood);
+
+    match &options.render {
+        Some(Render::Ansi) => {
+            print!("{}", render::render_ansi(&schematic, &options.rule));
+        }
+        Some(Render::Html(path)) => {
+            std::fs::write(path, render::render_html(&schematic, &options.rule))
+                .expect("Unable to write html file");
+            println!("Schematic written to {path}");
+        }
+        None => {}
+    }
+
+    if options.stats {
+        println!("symbol  count  parts  sum");
+        for (ch, stats) in schematic.symbol_stats() {
+            println!(
+                "{ch:>6}  {:>5}  {:>5}  {}",
+                stats.symbols, stats.parts, stats.sum
+            );
+        }
+    }
+
+    for (row, col) in options.queries.iter() {
+        match schematic.parts_at(*row, *col) {
+            Some(parts) => {
+                let numbers: Vec<_> = parts.iter().map(|part| part.number).collect();
+                println!("Parts touching ({row}, {col}): {numbers:?}");
+            }
+            None => println!("No symbol at ({row}, {col})"),
+        }
+    }
+
+    let result = schematic.part_sum();
     println!("Result part 1: {result}");
-    let result = part2(input);
+    let result = schematic.gear_ratio_sum(&options.rule);
     println!("Result part 2: {result}");
 }
 
@@ -168,7 +471,7 @@ fn test_part1() {
 ......755.
 ...$.*....
 .664.598..";
-    assert_eq!(part1(&input), 4361);
+    assert_eq!(part1(input), 4361);
 }
 
 #[test]
@@ -183,5 +486,314 @@ fn test_part2() {
 ......755.
 ...$.*....
 .664.598..";
-    assert_eq!(part2(&input), 467835);
+    assert_eq!(part2(input), 467835);
+}
+
+#[test]
+fn test_adjacency_graph() {
+    let schematic = parse(
+        "467..114..
+...*......
+..35..633.",
+    );
+
+    assert_eq!(schematic.parts.len(), 4);
+    assert_eq!(
+        schematic.parts[0],
+        EnginePart {
+            number: 467,
+            row: 0,
+            cols: 0..3
+        }
+    );
+    assert_eq!(
+        schematic.symbols,
+        vec![Symbol {
+            ch: '*',
+            row: 1,
+            col: 3
+        }]
+    );
+
+    let touching: Vec<u64> = schematic.parts_of(0).map(|part| part.number).collect();
+    assert_eq!(touching, vec![467, 35]);
+    assert!(!schematic.is_counted(1));
+    assert_eq!(schematic.symbols_of(2).count(), 1);
+}
+
+#[test]
+fn test_gear_neighbour_counts() {
+    /* stars touching one, two and three parts */
+    let input = "2*.......
+.........
+3*4......
+.........
+5*6......
+.7.......";
+
+    assert_eq!(part2(input), 12);
+
+    let rule = GearRule {
+        symbol: Some('*'),
+        parts: PartCount::AtLeast(2),
+    };
+    assert_eq!(parse(input).gear_ratio_sum(&rule), 12 + 5 * 6 * 7);
+
+    let rule = GearRule {
+        symbol: Some('*'),
+        parts: PartCount::Exactly(1),
+    };
+    assert_eq!(parse(input).gear_ratio_sum(&rule), 2);
+}
+
+#[test]
+fn test_gear_any_symbol() {
+    let input = "1#2
+...
+3*4";
+
+    let rule = GearRule {
+        symbol: None,
+        parts: PartCount::Exactly(2),
+    };
+    assert_eq!(parse(input).gear_ratio_sum(&rule), 2 + 12);
+    assert_eq!(part2(input), 12);
+
+    let args = ["--gear-symbol", "any", "--gear-parts", "3", "--at-least"];
+    let options = parse_args(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(
+        options.rule,
+        GearRule {
+            symbol: None,
+            parts: PartCount::AtLeast(3),
+        }
+    );
+}
+
+#[test]
+fn test_non_square_grid() {
+    /* wider than tall: parts beyond the row count must still see their symbols */
+    let input = "........12
+.........#
+..........";
+    assert_eq!(part1(input), 12);
+
+    /* taller than wide */
+    let input = "..
+.1
+..
+..
+#.
+5.";
+    assert_eq!(part1(input), 5);
+}
+
+#[test]
+fn test_number_at_end_of_grid() {
+    let input = "....
+..*.
+..42";
+    let schematic = parse(input);
+    assert_eq!(
+        schematic.parts,
+        vec![EnginePart {
+            number: 42,
+            row: 2,
+            cols: 2..4
+        }]
+    );
+    assert_eq!(part1(input), 42);
+
+    /* number ending a line is not merged with the next line's digits */
+    let input = "...*12
+34....";
+    assert_eq!(part1(input), 12);
+}
+
+#[test]
+fn test_render() {
+    let schematic = parse("1*2.\n..#.\n7..<");
+    let rule = GearRule::default();
+
+    let ansi = render::render_ansi(&schematic, &rule);
+    assert!(ansi.starts_with("\x1b[1;32m1\x1b[0m\x1b[1;33m*\x1b[0m\x1b[1;32m2\x1b[0m"));
+    assert!(ansi.contains("\x1b[31m7\x1b[0m"));
+    assert_eq!(ansi.lines().count(), 3);
+
+    let html = render::render_html(&schematic, &rule);
+    assert!(html.contains("<span class=\"gear\">*</span>"));
+    assert!(html.contains("<span class=\"symbol\">#</span>"));
+    assert!(html.contains("<span class=\"number\">7</span>"));
+    assert!(html.contains("<span class=\"symbol\">&lt;</span>"));
+}
+
+#[test]
+fn test_neighbourhoods() {
+    let input = "1....
+.*...
+..2#.
+.....
+...#4";
+
+    /* 1 only touches '*' diagonally */
+    assert_eq!(parse_with(input, Neighbourhood::Four).part_sum(), 2 + 4);
+    assert_eq!(
+        parse_with(input, Neighbourhood::Eight).part_sum(),
+        1 + 2 + 4
+    );
+
+    let schematic = parse_with(input, Neighbourhood::Radius(2));
+    let touching: Vec<_> = schematic
+        .parts_at(4, 3)
+        .unwrap()
+        .iter()
+        .map(|part| part.number)
+        .collect();
+    assert_eq!(touching, vec![2, 4]);
+    assert!(schematic.parts_at(0, 0).is_none());
+}
+
+#[test]
+fn test_symbol_stats() {
+    let input = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+    let stats = parse(input).symbol_stats();
+
+    assert_eq!(
+        stats[&'*'],
+        SymbolStats {
+            symbols: 3,
+            parts: 5,
+            sum: 467 + 35 + 617 + 755 + 598
+        }
+    );
+    assert_eq!(
+        stats[&'#'],
+        SymbolStats {
+            symbols: 1,
+            parts: 1,
+            sum: 633
+        }
+    );
+    assert_eq!(stats.values().map(|stat| stat.sum).sum::<u64>(), 4361);
+}
+
+#[cfg(test)]
+fn generate_schematic(width: usize, height: usize) -> String {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut out = String::with_capacity((width + 1) * height);
+
+    for _ in 0..height {
+        for _ in 0..width {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            out.push(match state % 16 {
+                0..=4 => char::from_digit((state / 16 % 10) as u32, 10).unwrap(),
+                5 => '*',
+                6 => '#',
+                7 => '+',
+                _ => '.',
+            });
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[test]
+fn test_scan_matches_schematic() {
+    let input = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+    let totals = scan::scan(input.as_bytes(), &GearRule::default()).unwrap();
+    assert_eq!(totals.part_sum, 4361);
+    assert_eq!(totals.gear_ratio_sum, 467835);
+
+    let input = generate_schematic(300, 200);
+    let schematic = parse(&input);
+    let rules = [
+        GearRule::default(),
+        GearRule {
+            symbol: None,
+            parts: PartCount::AtLeast(3),
+        },
+    ];
+    for rule in rules {
+        let totals = scan::scan(input.as_bytes(), &rule).unwrap();
+        assert_eq!(totals.part_sum, schematic.part_sum());
+        assert_eq!(
+            totals.gear_ratio_sum,
+            schematic
+                .gears(&rule)
+                .map(|symbol| schematic
+                    .parts_of(symbol)
+                    .map(|part| part.number)
+                    .product::<u64>())
+                .sum::<u64>()
+        );
+    }
+}
+
+#[test]
+fn test_gear_ratio_past_u32() {
+    let input = "999.998
+...*...
+997.996";
+    let rule = GearRule {
+        symbol: Some('*'),
+        parts: PartCount::AtLeast(4),
+    };
+    let schematic = parse(input);
+
+    /* 999 * 998 * 997 * 996 does not fit in a u32 */
+    assert_eq!(schematic.gear_ratio(0), 990_034_950_024);
+    assert_eq!(schematic.gear_ratio_sum(&rule), 990_034_950_024);
+    assert_eq!(
+        scan::scan(input.as_bytes(), &rule).unwrap().gear_ratio_sum,
+        990_034_950_024
+    );
+}
+
+#[test]
+fn test_long_part_numbers() {
+    /* eleven digits overflow a u32 */
+    let input = "12345678901*2";
+    let schematic = parse(input);
+    assert_eq!(schematic.parts[0].number, 12_345_678_901);
+    assert_eq!(schematic.part_sum(), 12_345_678_903);
+    assert_eq!(
+        schematic.gear_ratio_sum(&GearRule::default()),
+        24_691_357_802
+    );
+
+    let totals = scan::scan(input.as_bytes(), &GearRule::default()).unwrap();
+    assert_eq!(totals.part_sum, schematic.part_sum());
+    assert_eq!(
+        totals.gear_ratio_sum,
+        schematic.gear_ratio_sum(&GearRule::default())
+    );
+}
+
+#[test]
+#[should_panic(expected = "--stream only supports --neighbourhood 8")]
+fn test_stream_rejects_other_neighbourhoods() {
+    let args = ["--stream", "-", "--neighbourhood", "4"];
+    parse_args(args.iter().map(|arg| arg.to_string()));
 }
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use crate::{GearRule, Point, Schematic};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    Part,
+    Number,
+    Symbol,
+    Gear,
+}
+
+impl Cell {
+    fn ansi(&self) -> &'static str {
+        match self {
+            Cell::Empty => "\x1b[2m",
+            Cell::Part => "\x1b[1;32m",
+            Cell::Number => "\x1b[31m",
+            Cell::Symbol => "\x1b[36m",
+            Cell::Gear => "\x1b[1;33m",
+        }
+    }
+
+    fn class(&self) -> &'static str {
+        match self {
+            Cell::Empty => "empty",
+            Cell::Part => "part",
+            Cell::Number => "number",
+            Cell::Symbol => "symbol",
+            Cell::Gear => "gear",
+        }
+    }
+}
+
+/* classify every cell of the schematic, row by row */
+fn classify(schematic: &Schematic, rule: &GearRule) -> Vec<Vec<(char, Cell)>> {
+    let gears: HashSet<usize> = schematic.gears(rule).collect();
+    let mut rows: Vec<Vec<(char, Cell)>> = (0..schematic.height)
+        .map(|row| {
+            (0..schematic.width)
+                .map(|col| match schematic.get(row, col) {
+                    Some(Point::Part(digit)) => {
+                        let ch = char::from_digit(*digit, 10).expect("Not a digit");
+                        (ch, Cell::Number)
+                    }
+                    Some(Point::Symbol(ch)) => {
+                        let idx = schematic.symbol_index[&(row, col)];
+                        if gears.contains(&idx) {
+                            (*ch, Cell::Gear)
+                        } else {
+                            (*ch, Cell::Symbol)
+                        }
+                    }
+                    _ => ('.', Cell::Empty),
+                })
+                .collect()
+        })
+        .collect();
+
+    for (idx, part) in schematic.parts.iter().enumerate() {
+        if schematic.is_counted(idx) {
+            for col in part.cols.clone() {
+                rows[part.row][col].1 = Cell::Part;
+            }
+        }
+    }
+
+    rows
+}
+
+pub fn render_ansi(schematic: &Schematic, rule: &GearRule) -> String {
+    let mut out = String::new();
+
+    for row in classify(schematic, rule) {
+        for (ch, cell) in row {
+            out.push_str(cell.ansi());
+            out.push(ch);
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+pub fn render_html(schematic: &Schematic, rule: &GearRule) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Engine schematic</title>
+<style>
+body { background: #0f0f23; color: #cccccc; font-family: monospace; }
+.empty { color: #444444; }
+.part { color: #00cc00; font-weight: bold; }
+.number { color: #ff5555; }
+.symbol { color: #55cccc; }
+.gear { color: #ffff66; font-weight: bold; background: #333300; }
+</style>
+</head>
+<body>
+<p>
+<span class=\"part\">counted part</span>
+<span class=\"number\">uncounted number</span>
+<span class=\"symbol\">symbol</span>
+<span class=\"gear\">gear</span>
+</p>
+<pre>
+",
+    );
+
+    for row in classify(schematic, rule) {
+        for (ch, cell) in row {
+            let ch = match ch {
+                '&' => "&amp;".to_string(),
+                '<' => "&lt;".to_string(),
+                '>' => "&gt;".to_string(),
+                _ => ch.to_string(),
+            };
+            out.push_str(&format!("<span class=\"{}\">{ch}</span>", cell.class()));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("</pre>\n</body>\n</html>\n");
+
+    out
+}
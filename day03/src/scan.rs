@@ -0,0 +1,109 @@
+use std::io::{self, BufRead};
+use std::ops::Range;
+
+use crate::GearRule;
+
+/* Streaming evaluation of a schematic using a sliding window of three rows, so
+ * memory stays bounded by the row width no matter how many rows there are.
+ * Adjacency is the eight-cell neighbourhood.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScanTotals {
+    pub part_sum: u64,
+    pub gear_ratio_sum: u64,
+}
+
+struct Row {
+    cells: Vec<char>,
+    numbers: Vec<(u64, Range<usize>)>,
+}
+
+impl Row {
+    fn new(line: &str) -> Row {
+        let cells: Vec<char> = line.chars().collect();
+        let mut numbers = Vec::new();
+        let mut number: Option<(u64, usize)> = None;
+
+        for (col, ch) in cells.iter().enumerate() {
+            if let Some(digit) = ch.to_digit(10) {
+                let (value, start) = number.unwrap_or((0, col));
+                number = Some((value * 10 + digit as u64, start));
+            } else if let Some((value, start)) = number.take() {
+                numbers.push((value, start..col));
+            }
+        }
+        if let Some((value, start)) = number {
+            numbers.push((value, start..cells.len()));
+        }
+
+        Row { cells, numbers }
+    }
+
+    fn has_symbol(&self, cols: Range<usize>) -> bool {
+        cols.filter_map(|col| self.cells.get(col))
+            .any(|ch| *ch != '.' && !ch.is_ascii_digit())
+    }
+
+    /* numbers with at least one digit in the given columns */
+    fn numbers_touching(&self, cols: &Range<usize>) -> impl Iterator<Item = u64> + '_ {
+        let cols = cols.clone();
+        self.numbers
+            .iter()
+            .filter(move |(_, span)| span.start < cols.end && cols.start < span.end)
+            .map(|(value, _)| *value)
+    }
+}
+
+fn process(rows: [Option<&Row>; 3], rule: &GearRule, totals: &mut ScanTotals) {
+    let Some(cur) = rows[1] else {
+        return;
+    };
+
+    for (value, span) in cur.numbers.iter() {
+        let cols = span.start.saturating_sub(1)..span.end + 1;
+        if rows
+            .iter()
+            .flatten()
+            .any(|row| row.has_symbol(cols.clone()))
+        {
+            totals.part_sum += value;
+        }
+    }
+
+    for (col, ch) in cur.cells.iter().enumerate() {
+        if *ch == '.' || ch.is_ascii_digit() || rule.symbol.is_some_and(|sym| sym != *ch) {
+            continue;
+        }
+
+        let cols = col.saturating_sub(1)..col + 2;
+        let parts: Vec<u64> = rows
+            .iter()
+            .flatten()
+            .flat_map(|row| row.numbers_touching(&cols))
+            .collect();
+
+        if rule.parts.matches(parts.len()) {
+            totals.gear_ratio_sum += parts.iter().product::<u64>();
+        }
+    }
+}
+
+pub fn scan<R: BufRead>(reader: R, rule: &GearRule) -> io::Result<ScanTotals> {
+    let mut totals = ScanTotals::default();
+    let mut prev: Option<Row> = None;
+    let mut cur: Option<Row> = None;
+
+    for line in reader.lines() {
+        let next = Row::new(&line?);
+        process(
+            [prev.as_ref(), cur.as_ref(), Some(&next)],
+            rule,
+            &mut totals,
+        );
+        prev = cur;
+        cur = Some(next);
+    }
+    process([prev.as_ref(), cur.as_ref(), None], rule, &mut totals);
+
+    Ok(totals)
+}
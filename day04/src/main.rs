@@ -1,10 +1,194 @@
-use std::collections::HashSet;
+use num_bigint::BigUint;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::time::Instant;
+
+/* Card count or score: plain u64 while it fits, switching to an arbitrary
+ * precision integer once checked arithmetic overflows.
+ */
+#[derive(Debug, Clone)]
+enum Count {
+    Small(u64),
+    Big(BigUint),
+}
+
+impl Count {
+    fn to_big(&self) -> BigUint {
+        match self {
+            Count::Small(value) => BigUint::from(*value),
+            Count::Big(value) => value.clone(),
+        }
+    }
+
+    fn pow(base: u32, exp: u32) -> Count {
+        match (base as u64).checked_pow(exp) {
+            Some(value) => Count::Small(value),
+            None => Count::Big(BigUint::from(base).pow(exp)),
+        }
+    }
+
+    fn product(a: u64, b: u64) -> Count {
+        match a.checked_mul(b) {
+            Some(value) => Count::Small(value),
+            None => Count::Big(BigUint::from(a) * b),
+        }
+    }
+}
+
+impl Default for Count {
+    fn default() -> Self {
+        Count::Small(0)
+    }
+}
+
+impl From<u64> for Count {
+    fn from(value: u64) -> Self {
+        Count::Small(value)
+    }
+}
+
+impl PartialEq for Count {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Count::Small(a), Count::Small(b)) => a == b,
+            _ => self.to_big() == other.to_big(),
+        }
+    }
+}
+
+impl Eq for Count {}
+
+impl Add<&Count> for Count {
+    type Output = Count;
+
+    fn add(self, other: &Count) -> Count {
+        match (&self, other) {
+            (Count::Small(a), Count::Small(b)) => match a.checked_add(*b) {
+                Some(value) => Count::Small(value),
+                None => Count::Big(BigUint::from(*a) + *b),
+            },
+            (Count::Big(a), Count::Small(b)) => Count::Big(a + *b),
+            (_, Count::Big(b)) => Count::Big(self.to_big() + b),
+        }
+    }
+}
+
+impl AddAssign<&Count> for Count {
+    fn add_assign(&mut self, other: &Count) {
+        *self = std::mem::take(self) + other;
+    }
+}
+
+impl Sum for Count {
+    fn sum<I: Iterator<Item = Count>>(iter: I) -> Self {
+        iter.fold(Count::default(), |acc, count| acc + &count)
+    }
+}
+
+impl<'a> Sum<&'a Count> for Count {
+    fn sum<I: Iterator<Item = &'a Count>>(iter: I) -> Self {
+        iter.fold(Count::default(), |acc, count| acc + count)
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Count::Small(value) => write!(f, "{value}"),
+            Count::Big(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+const BITSET_WORDS: usize = 2;
+const BITSET_MAX: u32 = (BITSET_WORDS * 64) as u32;
+
+/* Set of card numbers: a fixed-width bitset when every number fits into it,
+ * otherwise a sorted, deduplicated vector.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NumberSet {
+    Bits([u64; BITSET_WORDS]),
+    Sorted(Vec<u32>),
+}
+
+impl Default for NumberSet {
+    fn default() -> Self {
+        NumberSet::Bits([0; BITSET_WORDS])
+    }
+}
+
+impl FromIterator<u32> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut numbers: Vec<u32> = iter.into_iter().collect();
+
+        if numbers.iter().all(|num| *num < BITSET_MAX) {
+            let mut bits = [0; BITSET_WORDS];
+            for num in numbers {
+                bits[num as usize / 64] |= 1 << (num % 64);
+            }
+            NumberSet::Bits(bits)
+        } else {
+            numbers.sort_unstable();
+            numbers.dedup();
+            NumberSet::Sorted(numbers)
+        }
+    }
+}
+
+impl NumberSet {
+    fn contains(&self, num: u32) -> bool {
+        match self {
+            NumberSet::Bits(bits) => {
+                num < BITSET_MAX && bits[num as usize / 64] & (1 << (num % 64)) != 0
+            }
+            NumberSet::Sorted(numbers) => numbers.binary_search(&num).is_ok(),
+        }
+    }
+
+    fn match_count(&self, other: &NumberSet) -> usize {
+        match (self, other) {
+            (NumberSet::Bits(a), NumberSet::Bits(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|(a, b)| (a & b).count_ones() as usize)
+                .sum(),
+            (NumberSet::Sorted(a), NumberSet::Sorted(b)) => {
+                let (mut i, mut j, mut count) = (0, 0, 0);
+                while i < a.len() && j < b.len() {
+                    match a[i].cmp(&b[j]) {
+                        std::cmp::Ordering::Less => i += 1,
+                        std::cmp::Ordering::Greater => j += 1,
+                        std::cmp::Ordering::Equal => {
+                            count += 1;
+                            i += 1;
+                            j += 1;
+                        }
+                    }
+                }
+                count
+            }
+            (bits @ NumberSet::Bits(_), NumberSet::Sorted(numbers))
+            | (NumberSet::Sorted(numbers), bits @ NumberSet::Bits(_)) => {
+                numbers.iter().filter(|num| bits.contains(**num)).count()
+            }
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 struct Card {
-    _id: u32,
-    num_win: HashSet<u32>,
-    num_own: HashSet<u32>,
+    id: u32,
+    num_win: NumberSet,
+    num_own: NumberSet,
+}
+
+impl Card {
+    fn matches(&self) -> usize {
+        self.num_win.match_count(&self.num_own)
+    }
 }
 
 fn parse(input: &str) -> Vec<Card> {
@@ -21,17 +205,17 @@ fn parse(input: &str) -> Vec<Card> {
 
             let (num_win, num_own) = rest.split_once('|').expect("Cannot parse line");
 
-            let num_win: HashSet<_> = num_win
+            let num_win: NumberSet = num_win
                 .split_ascii_whitespace()
                 .map(|str| str.parse::<u32>().expect("Cannot parse number"))
                 .collect();
-            let num_own: HashSet<_> = num_own
+            let num_own: NumberSet = num_own
                 .split_ascii_whitespace()
                 .map(|str| str.parse::<u32>().expect("Cannot parse number"))
                 .collect();
 
             Card {
-                _id: id,
+                id,
                 num_win,
                 num_own,
             }
@@ -39,50 +223,501 @@ fn parse(input: &str) -> Vec<Card> {
         .collect()
 }
 
-fn part1(input: &str) -> u32 {
-    let games = parse(input);
+/* Points awarded for a card with the given number of matches */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scoring {
+    Linear { points: u32 },
+    Exponential { base: u32 },
+    /* points indexed by match count, the last entry repeats for higher counts */
+    Table(Vec<u32>),
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring::Exponential { base: 2 }
+    }
+}
+
+impl Scoring {
+    fn score(&self, matches: usize) -> Count {
+        match self {
+            Scoring::Linear { points } => Count::product(matches as u64, *points as u64),
+            Scoring::Exponential { base } => {
+                if matches > 0 {
+                    Count::pow(*base, matches as u32 - 1)
+                } else {
+                    Count::default()
+                }
+            }
+            Scoring::Table(table) => {
+                let points = table.get(matches).or(table.last()).copied().unwrap_or(0);
+                Count::from(points as u64)
+            }
+        }
+    }
+}
+
+/* Which cards a winning card hands its copies to */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum CopyRule {
+    #[default]
+    Next,
+    Previous,
+    /* next N cards, continuing from the first card past the end; cards already
+     * processed receive the copies but do not hand them on again
+     */
+    WrapAround,
+    /* next N cards, but never more than the cap */
+    Capped(usize),
+}
+
+impl CopyRule {
+    /* positions of the cards receiving copies, which may fall outside the deck */
+    fn targets(&self, idx: usize, matches: usize, len: usize) -> Vec<i64> {
+        let (idx, matches) = (idx as i64, matches as i64);
+
+        match self {
+            CopyRule::Next => (idx + 1..=idx + matches).collect(),
+            CopyRule::Previous => (idx - matches..idx).collect(),
+            CopyRule::WrapAround => (1..=matches)
+                .map(|n| (idx + n).rem_euclid(len as i64))
+                .collect(),
+            CopyRule::Capped(cap) => (idx + 1..=idx + matches.min(*cap as i64)).collect(),
+        }
+    }
+
+    /* copies must be final before a card hands them on, so propagate in the
+     * direction the copies flow
+     */
+    fn order(&self, mut ids: Vec<u32>) -> Vec<u32> {
+        if *self == CopyRule::Previous {
+            ids.reverse();
+        }
+
+        ids
+    }
+}
+
+/* What to do with copies won for cards past either end of the deck */
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Overflow {
+    #[default]
+    Error,
+    Clip,
+    Wrap,
+}
+
+impl Overflow {
+    /* map a target position in the id range `first..first + len` back into it */
+    fn resolve(
+        &self,
+        card: &Card,
+        target: i64,
+        first: u32,
+        len: usize,
+    ) -> Result<Option<usize>, CopyError> {
+        if (0..len as i64).contains(&target) {
+            return Ok(Some(target as usize));
+        }
+
+        match self {
+            Overflow::Error => Err(CopyError::OutOfRange {
+                card: card.id,
+                target: first as i64 + target,
+                first,
+                last: (first as u64 + len as u64 - 1) as u32,
+            }),
+            Overflow::Clip => Ok(None),
+            Overflow::Wrap => Ok(Some(target.rem_euclid(len as i64) as usize)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IdIssue {
+    Duplicate {
+        id: u32,
+        line: usize,
+        first_line: usize,
+    },
+    OutOfOrder {
+        id: u32,
+        line: usize,
+        previous: u32,
+    },
+    Missing {
+        from: u32,
+        to: u32,
+    },
+}
+
+impl fmt::Display for IdIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdIssue::Duplicate {
+                id,
+                line,
+                first_line,
+            } => write!(
+                f,
+                "line {line}: card {id} already defined on line {first_line}"
+            ),
+            IdIssue::OutOfOrder { id, line, previous } => {
+                write!(f, "line {line}: card {id} comes after card {previous}")
+            }
+            IdIssue::Missing { from, to } if from == to => write!(f, "card {from} is missing"),
+            IdIssue::Missing { from, to } => write!(f, "cards {from} to {to} are missing"),
+        }
+    }
+}
 
-    games
-        .iter()
-        .map(|card| {
-            let count = card.num_win.intersection(&card.num_own).count();
-            if count > 0 {
-                2u32.pow(count as u32 - 1)
-            } else {
-                0
+/* Check that card ids are unique, ascending and without gaps; lines are
+ * numbered from 1 as in the input
+ */
+fn validate_ids(cards: &[Card]) -> Vec<IdIssue> {
+    let mut issues = Vec::new();
+    let mut seen: HashMap<u32, usize> = HashMap::new();
+
+    for (idx, card) in cards.iter().enumerate() {
+        let line = idx + 1;
+
+        if let Some(first_line) = seen.get(&card.id) {
+            issues.push(IdIssue::Duplicate {
+                id: card.id,
+                line,
+                first_line: *first_line,
+            });
+            continue;
+        }
+        seen.insert(card.id, line);
+
+        if let Some(previous) = idx.checked_sub(1).map(|prev| cards[prev].id) {
+            if card.id < previous {
+                issues.push(IdIssue::OutOfOrder {
+                    id: card.id,
+                    line,
+                    previous,
+                });
+            }
+        }
+    }
+
+    let mut ids: Vec<u32> = seen.into_keys().collect();
+    ids.sort_unstable();
+    for pair in ids.windows(2) {
+        if pair[1] > pair[0] + 1 {
+            issues.push(IdIssue::Missing {
+                from: pair[0] + 1,
+                to: pair[1] - 1,
+            });
+        }
+    }
+
+    issues
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CopyError {
+    OutOfRange {
+        card: u32,
+        target: i64,
+        first: u32,
+        last: u32,
+    },
+    MissingCard {
+        card: u32,
+        target: u32,
+    },
+    DuplicateIds(Vec<IdIssue>),
+}
+
+impl fmt::Display for CopyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CopyError::OutOfRange {
+                card,
+                target,
+                first,
+                last,
+            } => write!(
+                f,
+                "Card {card} wins a copy of card {target}, outside cards {first} to {last}"
+            ),
+            CopyError::MissingCard { card, target } => {
+                write!(
+                    f,
+                    "Card {card} wins a copy of card {target}, which is missing"
+                )
+            }
+            CopyError::DuplicateIds(issues) => {
+                write!(f, "Card ids are not unique:")?;
+                for issue in issues {
+                    write!(f, "\n  {issue}")?;
+                }
+                Ok(())
             }
+        }
+    }
+}
+
+impl std::error::Error for CopyError {}
+
+/* Final number of copies of a card, including the original, and the cards the
+ * extra copies were won from
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CardCopies {
+    id: u32,
+    copies: Count,
+    sources: Vec<(u32, Count)>,
+}
+
+/* Copies are handed on by card id: card N winning M cards copies cards N+1 to
+ * N+M, wherever they appear in the input. Result is ordered by id.
+ */
+fn propagate(
+    cards: &[Card],
+    rule: &CopyRule,
+    overflow: Overflow,
+) -> Result<Vec<CardCopies>, CopyError> {
+    let duplicates: Vec<IdIssue> = validate_ids(cards)
+        .into_iter()
+        .filter(|issue| matches!(issue, IdIssue::Duplicate { .. }))
+        .collect();
+    if !duplicates.is_empty() {
+        return Err(CopyError::DuplicateIds(duplicates));
+    }
+
+    let by_id: BTreeMap<u32, &Card> = cards.iter().map(|card| (card.id, card)).collect();
+    let (Some(first), Some(last)) = (by_id.keys().next(), by_id.keys().next_back()) else {
+        return Ok(Vec::new());
+    };
+    let (first, len) = (*first, (*last - *first) as usize + 1);
+
+    /* only ids present in the input, positions are id - first */
+    let mut result: BTreeMap<u32, CardCopies> = by_id
+        .keys()
+        .map(|id| {
+            let copies = CardCopies {
+                id: *id,
+                copies: Count::from(1),
+                sources: Vec::new(),
+            };
+            (*id, copies)
         })
-        .sum::<u32>()
+        .collect();
+
+    for id in rule.order(by_id.keys().copied().collect()) {
+        let card = by_id[&id];
+        let num_cards = result[&id].copies.clone();
+
+        for target in rule.targets((id - first) as usize, card.matches(), len) {
+            let Some(target) = overflow.resolve(card, target, first, len)? else {
+                continue;
+            };
+            let target = first + target as u32;
+
+            let Some(entry) = result.get_mut(&target) else {
+                match overflow {
+                    Overflow::Error => {
+                        return Err(CopyError::MissingCard {
+                            card: card.id,
+                            target,
+                        })
+                    }
+                    _ => continue,
+                }
+            };
+
+            entry.copies += &num_cards;
+            match entry.sources.last_mut() {
+                Some((id, count)) if *id == card.id => *count += &num_cards,
+                _ => entry.sources.push((card.id, num_cards.clone())),
+            }
+        }
+    }
+
+    Ok(result.into_values().collect())
+}
+
+#[cfg(test)]
+fn part1(input: &str) -> Count {
+    part1_with_scoring(input, &Scoring::default())
 }
 
-fn part2(input: &str) -> u32 {
+fn part1_with_scoring(input: &str, scoring: &Scoring) -> Count {
     let games = parse(input);
 
-    let winning_count: Vec<u32> = games
-        .iter()
-        .map(|card| card.num_win.intersection(&card.num_own).count() as u32)
-        .collect();
+    games.iter().map(|card| scoring.score(card.matches())).sum()
+}
+
+#[cfg(test)]
+fn part2(input: &str) -> Result<Count, CopyError> {
+    part2_with_rule(input, &CopyRule::default())
+}
+
+#[cfg(test)]
+fn part2_with_rule(input: &str, rule: &CopyRule) -> Result<Count, CopyError> {
+    let games = parse(input);
+    let cards = propagate(&games, rule, Overflow::default())?;
+
+    Ok(cards.into_iter().map(|card| card.copies).sum())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Options {
+    scoring: Scoring,
+    copies: CopyRule,
+    overflow: Overflow,
+    breakdown: bool,
+    bench: Option<usize>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options::default();
 
-    winning_count
-        .iter()
-        .enumerate()
-        .scan(vec![1; winning_count.len()], |counts, (idx, count)| {
-            let num_cards = counts[idx];
-            for i in idx + 1..=idx + *count as usize {
-                counts[i] += num_cards;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bench" => {
+                let count = args
+                    .next()
+                    .map(|count| count.parse::<usize>().expect("Card count must be a number"))
+                    .unwrap_or(1_000_000);
+                options.bench = Some(count);
             }
-            Some(num_cards)
+            "--scoring" => {
+                let value = args.next().expect("Missing scoring");
+                let (kind, param) = value.split_once(':').unwrap_or((&value, ""));
+                options.scoring = match kind {
+                    "linear" => Scoring::Linear {
+                        points: param.parse().unwrap_or(1),
+                    },
+                    "exp" => Scoring::Exponential {
+                        base: param.parse().unwrap_or(2),
+                    },
+                    "table" => Scoring::Table(
+                        param
+                            .split(',')
+                            .map(|points| points.parse().expect("Table entries must be numbers"))
+                            .collect(),
+                    ),
+                    _ => panic!("Scoring must be one of: linear[:N], exp[:B], table:A,B,.."),
+                };
+            }
+            "--copies" => {
+                let value = args.next().expect("Missing copy rule");
+                options.copies = match value.split_once(':') {
+                    Some(("capped", cap)) => {
+                        CopyRule::Capped(cap.parse().expect("Cap must be a number"))
+                    }
+                    None if value == "next" => CopyRule::Next,
+                    None if value == "previous" => CopyRule::Previous,
+                    None if value == "wrap" => CopyRule::WrapAround,
+                    _ => panic!("Copy rule must be one of: next, previous, wrap, capped:N"),
+                };
+            }
+            "--overflow" => {
+                options.overflow = match args.next().as_deref() {
+                    Some("error") => Overflow::Error,
+                    Some("clip") => Overflow::Clip,
+                    Some("wrap") => Overflow::Wrap,
+                    _ => panic!("Overflow must be one of: error, clip, wrap"),
+                };
+            }
+            "--breakdown" => options.breakdown = true,
+            _ => panic!("Unknown argument: {arg}"),
+        }
+    }
+
+    options
+}
+
+/* xorshift generated deck, with `large` numbers drawn beyond the bitset range */
+fn generate_cards(count: usize, large: bool) -> Vec<(Vec<u32>, Vec<u32>)> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let range = if large { 100_000 } else { 100 };
+        (state % range) as u32
+    };
+
+    (0..count)
+        .map(|_| {
+            let win = (0..10).map(|_| next()).collect();
+            let own = (0..25).map(|_| next()).collect();
+            (win, own)
         })
-        .sum()
+        .collect()
+}
+
+fn bench(count: usize) {
+    for large in [false, true] {
+        let deck = generate_cards(count, large);
+
+        let hash_sets: Vec<(HashSet<u32>, HashSet<u32>)> = deck
+            .iter()
+            .map(|(win, own)| (win.iter().copied().collect(), own.iter().copied().collect()))
+            .collect();
+        let start = Instant::now();
+        let hash_total: usize = hash_sets
+            .iter()
+            .map(|(win, own)| win.intersection(own).count())
+            .sum();
+        let hash_time = start.elapsed();
+
+        let number_sets: Vec<(NumberSet, NumberSet)> = deck
+            .iter()
+            .map(|(win, own)| (win.iter().copied().collect(), own.iter().copied().collect()))
+            .collect();
+        let start = Instant::now();
+        let set_total: usize = number_sets
+            .iter()
+            .map(|(win, own)| win.match_count(own))
+            .sum();
+        let set_time = start.elapsed();
+
+        assert_eq!(hash_total, set_total);
+        let kind = if large { "sorted" } else { "bitset" };
+        println!("{count} cards, {kind}: HashSet {hash_time:?}, NumberSet {set_time:?}");
+    }
 }
 
 fn main() {
     let input = include_str!("../input.txt");
 
-    let result = part1(input);
+    let options = parse_args(std::env::args().skip(1));
+
+    if let Some(count) = options.bench {
+        bench(count);
+        return;
+    }
+
+    let cards = parse(input);
+    for issue in validate_ids(&cards) {
+        println!("Warning: {issue}");
+    }
+
+    let result = part1_with_scoring(input, &options.scoring);
     println!("Result part 1: {result}");
-    let result = part2(input);
-    println!("Result part 2: {result}");
+    match propagate(&cards, &options.copies, options.overflow) {
+        Ok(cards) => {
+            if options.breakdown {
+                for card in cards.iter() {
+                    let sources: Vec<String> = card
+                        .sources
+                        .iter()
+                        .map(|(id, count)| format!("{count} from card {id}"))
+                        .collect();
+                    println!("Card {}: {} [{}]", card.id, card.copies, sources.join(", "));
+                }
+            }
+            let result: Count = cards.into_iter().map(|card| card.copies).sum();
+            println!("Result part 2: {result}");
+        }
+        Err(err) => println!("Part 2 failed: {err}"),
+    }
 }
 
 #[test]
@@ -93,7 +728,7 @@ Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
 Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
 Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
 Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
-    assert_eq!(part1(input), 13);
+    assert_eq!(part1(input), Count::from(13));
 }
 
 #[test]
@@ -104,5 +739,258 @@ Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
 Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
 Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
 Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
-    assert_eq!(part2(input), 30);
+    assert_eq!(part2(input), Ok(Count::from(30)));
+}
+
+#[test]
+fn test_number_set_matches_hash_set() {
+    for large in [false, true] {
+        for (win, own) in generate_cards(500, large) {
+            let expected = win
+                .iter()
+                .collect::<HashSet<_>>()
+                .intersection(&own.iter().collect::<HashSet<_>>())
+                .count();
+
+            let win_set: NumberSet = win.iter().copied().collect();
+            let own_set: NumberSet = own.iter().copied().collect();
+            assert_eq!(win_set.match_count(&own_set), expected);
+            assert_eq!(own_set.match_count(&win_set), expected);
+        }
+    }
+
+    /* mixed representations */
+    let small: NumberSet = [1, 5, 127].into_iter().collect();
+    let large: NumberSet = [5, 127, 128, 5000].into_iter().collect();
+    assert!(matches!(small, NumberSet::Bits(_)));
+    assert!(matches!(large, NumberSet::Sorted(_)));
+    assert_eq!(small.match_count(&large), 2);
+}
+
+#[test]
+fn test_scoring() {
+    let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    /* matches per card: 4, 2, 2, 1, 0, 0 */
+    assert_eq!(
+        part1_with_scoring(input, &Scoring::Linear { points: 1 }),
+        Count::from(9)
+    );
+    assert_eq!(
+        part1_with_scoring(input, &Scoring::Exponential { base: 3 }),
+        Count::from(27 + 3 + 3 + 1)
+    );
+    assert_eq!(
+        part1_with_scoring(input, &Scoring::Table(vec![0, 5, 7])),
+        Count::from(7 + 7 + 7 + 5)
+    );
+
+    let args = ["--scoring", "table:0,5,7", "--copies", "capped:1"];
+    let options = parse_args(args.iter().map(|arg| arg.to_string()));
+    assert_eq!(options.scoring, Scoring::Table(vec![0, 5, 7]));
+    assert_eq!(options.copies, CopyRule::Capped(1));
+}
+
+#[test]
+fn test_copy_rules() {
+    let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    /* counts: 1, 2, 3, 4, 5, 1 */
+    assert_eq!(
+        part2_with_rule(input, &CopyRule::Capped(1)),
+        Ok(Count::from(16))
+    );
+    /* counts: 1, 2, 4, 8, 14, 1 */
+    assert_eq!(
+        part2_with_rule(input, &CopyRule::WrapAround),
+        Ok(Count::from(30))
+    );
+
+    let input = "Card 1: 1 | 2
+Card 2: 1 | 1
+Card 3: 1 2 | 1 2";
+    /* card 3 copies cards 1 and 2, then card 2 copies card 1 twice: 1 + 2 + 1 */
+    assert_eq!(
+        part2_with_rule(input, &CopyRule::Previous),
+        Ok(Count::from(4 + 2 + 1))
+    );
+    /* card 3 wraps around to cards 1 and 2 */
+    assert_eq!(
+        part2_with_rule(input, &CopyRule::WrapAround),
+        Ok(Count::from(3 + 3 + 2))
+    );
+}
+
+#[test]
+fn test_large_counts() {
+    /* 70 matches: 2^69 points does not fit into u64 */
+    let numbers: Vec<String> = (1..=70).map(|num| num.to_string()).collect();
+    let input = format!("Card 1: {} | {}", numbers.join(" "), numbers.join(" "));
+    assert_eq!(part1(&input), Count::Big(BigUint::from(2u32).pow(69)));
+
+    /* every card but the last two copies the next two, so copy counts grow
+     * exponentially; the reference uses u128
+     */
+    let cards = 100;
+    let input: Vec<String> = (1..=cards)
+        .map(|id| match cards - id {
+            0 => format!("Card {id}: 1 | 2"),
+            1 => format!("Card {id}: 1 | 1"),
+            _ => format!("Card {id}: 1 2 | 1 2"),
+        })
+        .collect();
+    let mut counts = vec![1u128; cards];
+    for idx in 0..cards {
+        let wins = (cards - idx - 1).min(2);
+        for target in idx + 1..=idx + wins {
+            counts[target] += counts[idx];
+        }
+    }
+    let expected: u128 = counts.iter().sum();
+    assert!(expected > u64::MAX as u128);
+    assert_eq!(
+        part2(&input.join("\n")).unwrap().to_string(),
+        expected.to_string()
+    );
+}
+
+#[test]
+fn test_copy_overflow() {
+    let input = "Card 1: 1 | 1
+Card 2: 1 2 3 | 1 2 3
+Card 3: 1 | 2";
+    let cards = parse(input);
+
+    assert_eq!(
+        propagate(&cards, &CopyRule::Next, Overflow::Error),
+        Err(CopyError::OutOfRange {
+            card: 2,
+            target: 4,
+            first: 1,
+            last: 3
+        })
+    );
+
+    /* card 2 (2 copies) hands copies to card 3 only */
+    let clipped = propagate(&cards, &CopyRule::Next, Overflow::Clip).unwrap();
+    assert_eq!(
+        clipped[2],
+        CardCopies {
+            id: 3,
+            copies: Count::from(3),
+            sources: vec![(2, Count::from(2))],
+        }
+    );
+
+    /* card 2 also wraps around to cards 1 and 2 */
+    let wrapped = propagate(&cards, &CopyRule::Next, Overflow::Wrap).unwrap();
+    let copies: Vec<Count> = wrapped.iter().map(|card| card.copies.clone()).collect();
+    assert_eq!(copies, vec![Count::from(3), Count::from(4), Count::from(3)]);
+    assert_eq!(
+        wrapped[1].sources,
+        vec![(1, Count::from(1)), (2, Count::from(2))]
+    );
+
+    let err = propagate(&cards, &CopyRule::Previous, Overflow::Error).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Card 2 wins a copy of card -1, outside cards 1 to 3"
+    );
+
+    /* the default rule reports copies past the last card instead of panicking */
+    assert_eq!(
+        part2("Card 1: 1 | 2\nCard 2: 1 2 | 1 2")
+            .unwrap_err()
+            .to_string(),
+        "Card 2 wins a copy of card 3, outside cards 1 to 2"
+    );
+}
+
+#[test]
+fn test_validate_ids() {
+    let input = "Card 2: 1 | 1
+Card 1: 1 | 2
+Card 5: 1 | 2
+Card 2: 1 | 2";
+    let cards = parse(input);
+
+    assert_eq!(
+        validate_ids(&cards),
+        vec![
+            IdIssue::OutOfOrder {
+                id: 1,
+                line: 2,
+                previous: 2
+            },
+            IdIssue::Duplicate {
+                id: 2,
+                line: 4,
+                first_line: 1
+            },
+            IdIssue::Missing { from: 3, to: 4 },
+        ]
+    );
+
+    let err = propagate(&cards, &CopyRule::Next, Overflow::Clip).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Card ids are not unique:\n  line 4: card 2 already defined on line 1"
+    );
+}
+
+#[test]
+fn test_copies_by_id() {
+    /* out of order: card 1 still copies card 2, which then copies card 3 */
+    let input = "Card 3: 1 | 2
+Card 2: 1 | 1
+Card 1: 1 | 1";
+    let copies = propagate(&parse(input), &CopyRule::Next, Overflow::Error).unwrap();
+    let ids: Vec<u32> = copies.iter().map(|card| card.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(copies[2].copies, Count::from(3));
+    assert_eq!(part2(input), Ok(Count::from(1 + 2 + 3)));
+
+    /* card 3 is missing */
+    let input = "Card 1: 1 | 2
+Card 2: 1 | 1
+Card 4: 1 | 2";
+    let err = propagate(&parse(input), &CopyRule::Next, Overflow::Error).unwrap_err();
+    assert_eq!(err, CopyError::MissingCard { card: 2, target: 3 });
+    let copies = propagate(&parse(input), &CopyRule::Next, Overflow::Clip).unwrap();
+    assert_eq!(copies.len(), 3);
+
+    /* a huge gap between ids costs nothing */
+    let input = "Card 1: 1 | 1
+Card 3000000000: 1 | 1";
+    let err = propagate(&parse(input), &CopyRule::Next, Overflow::Error).unwrap_err();
+    assert_eq!(err, CopyError::MissingCard { card: 1, target: 2 });
+    let copies = propagate(&parse(input), &CopyRule::Next, Overflow::Clip).unwrap();
+    assert_eq!(copies.len(), 2);
+    assert_eq!(copies[1].copies, Count::from(1));
+
+    /* ids spanning all of u32 */
+    let input = "Card 0: 1 | 2
+Card 4294967295: 1 | 1";
+    let err = propagate(&parse(input), &CopyRule::Next, Overflow::Error).unwrap_err();
+    assert_eq!(
+        err,
+        CopyError::OutOfRange {
+            card: 4294967295,
+            target: 4294967296,
+            first: 0,
+            last: 4294967295,
+        }
+    );
+    let copies = propagate(&parse(input), &CopyRule::WrapAround, Overflow::Wrap).unwrap();
+    assert_eq!(copies[0].copies, Count::from(2));
 }
@@ -1,19 +1,76 @@
+mod render;
+
 use nom::{
-    bytes::complete::{tag, take_until},
-    character::complete::{multispace0, multispace1, u64},
+    bytes::complete::tag,
+    character::complete::{alpha1, multispace0, space1, u64},
+    error::{ErrorKind, ParseError},
     multi::{many1, separated_list1},
     sequence::tuple,
-    IResult,
+    IResult, Offset,
 };
+use std::collections::{HashMap, VecDeque};
 use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 struct Mapping {
     src: Range<u64>,
     dst: Range<u64>,
+    /* line in the almanac this mapping was read from */
+    line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RangeError {
+    /* `start + len` does not fit in a u64 */
+    Overflow { start: u64, len: u64 },
+    Empty { start: u64 },
+    /* seed ranges come in start/length pairs */
+    OddSeedCount(usize),
+}
+
+impl std::fmt::Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RangeError::Overflow { start, len } => {
+                write!(f, "range {start} of length {len} ends past {}", u64::MAX)
+            }
+            RangeError::Empty { start } => write!(f, "range at {start} has length 0"),
+            RangeError::OddSeedCount(count) => {
+                write!(f, "{count} seed values can not be paired into ranges")
+            }
+        }
+    }
+}
+
+/* `start..start + len`, which may end exactly at u64::MAX */
+fn checked_range(start: u64, len: u64) -> Result<Range<u64>, RangeError> {
+    if len == 0 {
+        return Err(RangeError::Empty { start });
+    }
+    let end = start
+        .checked_add(len)
+        .ok_or(RangeError::Overflow { start, len })?;
+
+    Ok(start..end)
+}
+
+fn seed_ranges(seeds: &[u64]) -> Result<Vec<Range<u64>>, RangeError> {
+    if !seeds.len().is_multiple_of(2) {
+        return Err(RangeError::OddSeedCount(seeds.len()));
+    }
+
+    seeds.chunks(2).map(|v| checked_range(v[0], v[1])).collect()
 }
 
 impl Mapping {
+    fn new(dst: u64, src: u64, len: u64, line: usize) -> Result<Mapping, RangeError> {
+        Ok(Mapping {
+            src: checked_range(src, len)?,
+            dst: checked_range(dst, len)?,
+            line,
+        })
+    }
+
     fn translate(&self, input: u64) -> Option<u64> {
         if self.src.contains(&input) {
             let diff = input - self.src.start;
@@ -22,33 +79,633 @@ impl Mapping {
             None
         }
     }
+
+    /* split `input` into the part this mapping translates and the parts it leaves untouched */
+    fn translate_range(&self, input: &Range<u64>) -> (Option<Range<u64>>, Vec<Range<u64>>) {
+        let start = input.start.max(self.src.start);
+        let end = input.end.min(self.src.end);
+
+        if start >= end {
+            return (None, vec![input.clone()]);
+        }
+
+        let translated =
+            self.dst.start + (start - self.src.start)..self.dst.start + (end - self.src.start);
+        let mut rest = Vec::new();
+        if input.start < start {
+            rest.push(input.start..start);
+        }
+        if end < input.end {
+            rest.push(end..input.end);
+        }
+
+        (Some(translated), rest)
+    }
 }
 
-fn parse_map(input: &str) -> IResult<&str, Vec<Mapping>> {
-    let (input, (_, _, _, values)) = tuple((
-        take_until("map:"),
-        tag("map:"),
-        multispace1,
-        separated_list1(multispace0, u64),
-    ))(input)?;
+/* push whole intervals through one layer, the first matching mapping wins and
+ * whatever no mapping covers passes through unchanged
+ */
+fn translate_ranges(layer: &[Mapping], ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    let mut pending = ranges;
+    let mut translated = Vec::new();
 
-    let mappings: Vec<_> = values
-        .chunks(3)
-        .map(|v| Mapping {
-            src: v[1]..v[1] + v[2],
-            dst: v[0]..v[0] + v[2],
-        })
-        .collect();
+    for map in layer {
+        let mut rest = Vec::new();
+        for range in pending.iter() {
+            let (hit, miss) = map.translate_range(range);
+            translated.extend(hit);
+            rest.extend(miss);
+        }
+        pending = rest;
+    }
+
+    translated.extend(pending);
+    translated
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    src: Range<u64>,
+    offset: i128,
+}
+
+impl Segment {
+    fn shift(&self, range: &Range<u64>) -> Range<u64> {
+        (range.start as i128 + self.offset) as u64..(range.end as i128 + self.offset) as u64
+    }
+
+    fn image(&self) -> Range<u64> {
+        self.shift(&self.src)
+    }
+}
+
+/* Piecewise-linear map over 0..u64::MAX: sorted, contiguous segments, each
+ * shifting its source range by a constant offset. Values not covered by any
+ * almanac mapping get identity segments.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PiecewiseMap {
+    segments: Vec<Segment>,
+}
+
+impl PiecewiseMap {
+    fn identity() -> PiecewiseMap {
+        PiecewiseMap {
+            segments: vec![Segment {
+                src: 0..u64::MAX,
+                offset: 0,
+            }],
+        }
+    }
+
+    fn from_layer(layer: &[Mapping]) -> PiecewiseMap {
+        let mut unclaimed = vec![Range {
+            start: 0,
+            end: u64::MAX,
+        }];
+        let mut segments = Vec::new();
+
+        for map in layer {
+            let offset = map.dst.start as i128 - map.src.start as i128;
+            let mut rest = Vec::new();
+
+            for range in unclaimed {
+                let start = range.start.max(map.src.start);
+                let end = range.end.min(map.src.end);
+
+                if start < end {
+                    segments.push(Segment {
+                        src: start..end,
+                        offset,
+                    });
+                    if range.start < start {
+                        rest.push(range.start..start);
+                    }
+                    if end < range.end {
+                        rest.push(end..range.end);
+                    }
+                } else {
+                    rest.push(range);
+                }
+            }
+            unclaimed = rest;
+        }
+
+        segments.extend(unclaimed.into_iter().map(|src| Segment { src, offset: 0 }));
+        segments.sort_by_key(|segment| segment.src.start);
+
+        PiecewiseMap { segments }.simplify()
+    }
+
+    /* segments overlapping `range`, in order */
+    fn overlapping<'a>(&'a self, range: &'a Range<u64>) -> impl Iterator<Item = &'a Segment> {
+        let first = self
+            .segments
+            .partition_point(|segment| segment.src.end <= range.start);
+
+        self.segments[first..]
+            .iter()
+            .take_while(|segment| segment.src.start < range.end)
+    }
+
+    fn eval(&self, input: u64) -> u64 {
+        self.overlapping(&(input..input.saturating_add(1)))
+            .next()
+            .map(|segment| (input as i128 + segment.offset) as u64)
+            .unwrap_or(input)
+    }
+
+    fn eval_range(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+        self.overlapping(range)
+            .map(|segment| {
+                let start = range.start.max(segment.src.start);
+                let end = range.end.min(segment.src.end);
+                segment.shift(&(start..end))
+            })
+            .collect()
+    }
+
+    /* map applying `self` first and `then` to its result */
+    fn compose(&self, then: &PiecewiseMap) -> PiecewiseMap {
+        let mut segments = Vec::new();
+
+        for segment in self.segments.iter() {
+            let image = segment.image();
+            for next in then.overlapping(&image) {
+                let start = image.start.max(next.src.start);
+                let end = image.end.min(next.src.end);
+                segments.push(Segment {
+                    src: (start as i128 - segment.offset) as u64
+                        ..(end as i128 - segment.offset) as u64,
+                    offset: segment.offset + next.offset,
+                });
+            }
+        }
+
+        PiecewiseMap { segments }.simplify()
+    }
+
+    /* all inputs mapped into `range`, as sorted disjoint ranges */
+    fn preimage(&self, range: &Range<u64>) -> Vec<Range<u64>> {
+        let mut result: Vec<Range<u64>> = Vec::new();
+
+        for segment in self.segments.iter() {
+            let image = segment.image();
+            let start = image.start.max(range.start);
+            let end = image.end.min(range.end);
+            if start >= end {
+                continue;
+            }
+
+            let src =
+                (start as i128 - segment.offset) as u64..(end as i128 - segment.offset) as u64;
+            match result.last_mut() {
+                Some(last) if last.end == src.start => last.end = src.end,
+                _ => result.push(src),
+            }
+        }
+
+        result
+    }
+
+    /* only bijective maps have an inverse */
+    fn invert(&self) -> Option<PiecewiseMap> {
+        let mut segments: Vec<Segment> = self
+            .segments
+            .iter()
+            .map(|segment| Segment {
+                src: segment.image(),
+                offset: -segment.offset,
+            })
+            .collect();
+        segments.sort_by_key(|segment| segment.src.start);
+
+        let mut end = 0;
+        for segment in segments.iter() {
+            if segment.src.start != end {
+                return None;
+            }
+            end = segment.src.end;
+        }
+        if end != u64::MAX {
+            return None;
+        }
+
+        Some(PiecewiseMap { segments })
+    }
+
+    /* merge neighbouring segments with the same offset */
+    fn simplify(self) -> PiecewiseMap {
+        let mut segments: Vec<Segment> = Vec::with_capacity(self.segments.len());
+
+        for segment in self.segments {
+            match segments.last_mut() {
+                Some(last)
+                    if last.offset == segment.offset && last.src.end == segment.src.start =>
+                {
+                    last.src.end = segment.src.end;
+                }
+                _ => segments.push(segment),
+            }
+        }
+
+        PiecewiseMap { segments }
+    }
+}
+
+impl std::fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for segment in self.segments.iter() {
+            let image = segment.image();
+            writeln!(
+                f,
+                "{}..{} -> {}..{}",
+                segment.src.start, segment.src.end, image.start, image.end
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn collapse(mappings: &[Vec<Mapping>]) -> PiecewiseMap {
+    mappings
+        .iter()
+        .map(|layer| PiecewiseMap::from_layer(layer))
+        .fold(PiecewiseMap::identity(), |acc, layer| acc.compose(&layer))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Issue {
+    /* values claimed by two mappings, the first one listed wins */
+    SourceOverlap {
+        layer: String,
+        lines: (usize, usize),
+        range: Range<u64>,
+    },
+    /* values two mappings both produce, so the layer is not injective */
+    DestinationOverlap {
+        layer: String,
+        lines: (usize, usize),
+        range: Range<u64>,
+    },
+    /* values between two mappings that fall through unchanged */
+    Gap {
+        layer: String,
+        lines: (usize, usize),
+        range: Range<u64>,
+    },
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (kind, layer, lines, range) = match self {
+            Issue::SourceOverlap {
+                layer,
+                lines,
+                range,
+            } => ("source ranges overlap", layer, lines, range),
+            Issue::DestinationOverlap {
+                layer,
+                lines,
+                range,
+            } => ("destination ranges overlap", layer, lines, range),
+            Issue::Gap {
+                layer,
+                lines,
+                range,
+            } => ("source ranges leave a gap", layer, lines, range),
+        };
+
+        write!(
+            f,
+            "lines {} and {}: {layer} {kind} on {}..{}",
+            lines.0, lines.1, range.start, range.end
+        )
+    }
+}
+
+/* two mappings and the range they share or leave between them */
+type Pair<'a> = (&'a Mapping, &'a Mapping, Range<u64>);
+
+/* pairs of mappings whose ranges (as picked by `key`) overlap, and the gaps
+ * between consecutive ones
+ */
+fn overlaps_and_gaps(
+    layer: &[Mapping],
+    key: impl Fn(&Mapping) -> &Range<u64>,
+) -> (Vec<Pair<'_>>, Vec<Pair<'_>>) {
+    let mut sorted: Vec<&Mapping> = layer.iter().filter(|map| !key(map).is_empty()).collect();
+    sorted.sort_by_key(|map| (key(map).start, map.line));
+
+    let mut overlaps = Vec::new();
+    for (idx, a) in sorted.iter().enumerate() {
+        for b in sorted[idx + 1..].iter() {
+            if key(b).start >= key(a).end {
+                break;
+            }
+            let range = key(b).start..key(a).end.min(key(b).end);
+            let (first, second) = if a.line < b.line { (a, b) } else { (b, a) };
+            overlaps.push((*first, *second, range));
+        }
+    }
+
+    let mut gaps = Vec::new();
+    let mut covered: Option<(&Mapping, u64)> = None;
+    for map in sorted {
+        if let Some((prev, end)) = covered {
+            if key(map).start > end {
+                let (first, second) = if prev.line < map.line {
+                    (prev, map)
+                } else {
+                    (map, prev)
+                };
+                gaps.push((first, second, end..key(map).start));
+            }
+        }
+        covered = match covered {
+            Some((prev, end)) if end >= key(map).end => Some((prev, end)),
+            _ => Some((map, key(map).end)),
+        };
+    }
+
+    (overlaps, gaps)
+}
+
+fn validate(almanac: &Almanac) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for layer in almanac.layers.iter() {
+        let name = format!("{}-to-{}", layer.from, layer.to);
+
+        let (overlaps, gaps) = overlaps_and_gaps(&layer.mappings, |map| &map.src);
+        issues.extend(
+            overlaps
+                .into_iter()
+                .map(|(a, b, range)| Issue::SourceOverlap {
+                    layer: name.clone(),
+                    lines: (a.line, b.line),
+                    range,
+                }),
+        );
+        issues.extend(gaps.into_iter().map(|(a, b, range)| Issue::Gap {
+            layer: name.clone(),
+            lines: (a.line, b.line),
+            range,
+        }));
+
+        let (overlaps, _) = overlaps_and_gaps(&layer.mappings, |map| &map.dst);
+        issues.extend(
+            overlaps
+                .into_iter()
+                .map(|(a, b, range)| Issue::DestinationOverlap {
+                    layer: name.clone(),
+                    lines: (a.line, b.line),
+                    range,
+                }),
+        );
+    }
+
+    issues
+}
+
+/* sort ranges and merge the overlapping or touching ones */
+fn normalize(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| range.start);
+
+    let mut result: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match result.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => result.push(range),
+        }
+    }
+
+    result
+}
+
+/* do two normalized range lists share any value */
+fn intersects(a: &[Range<u64>], b: &[Range<u64>]) -> bool {
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i].start < b[j].end && b[j].start < a[i].end {
+            return true;
+        }
+        if a[i].end <= b[j].end {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    false
+}
+
+fn reverse_through(maps: &[PiecewiseMap], ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    maps.iter().rev().fold(normalize(ranges), |ranges, map| {
+        normalize(
+            ranges
+                .iter()
+                .flat_map(|range| map.preimage(range))
+                .collect(),
+        )
+    })
+}
+
+/* One "<from>-to-<to> map:" block of the almanac */
+#[derive(Debug, Clone)]
+struct Layer {
+    from: String,
+    to: String,
+    mappings: Vec<Mapping>,
+}
+
+#[derive(Debug)]
+struct Almanac {
+    seeds: Vec<u64>,
+    layers: Vec<Layer>,
+}
+
+impl Almanac {
+    /* layers leading from one category to another, following the category
+     * graph regardless of the order the maps appear in
+     */
+    fn chain(&self, from: &str, to: &str) -> Option<Vec<&Layer>> {
+        let mut previous: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut chain = Vec::new();
+                let mut category = to;
+                while category != from {
+                    let layer = &self.layers[previous[category]];
+                    chain.push(layer);
+                    category = &layer.from;
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+
+            for (idx, layer) in self.layers.iter().enumerate() {
+                if layer.from == category
+                    && layer.to != from
+                    && !previous.contains_key(layer.to.as_str())
+                {
+                    previous.insert(&layer.to, idx);
+                    queue.push_back(&layer.to);
+                }
+            }
+        }
+
+        None
+    }
+
+    /* e.g. translate("soil", 81, "humidity") */
+    fn translate(&self, from: &str, value: u64, to: &str) -> Option<u64> {
+        let chain = self.chain(from, to)?;
+
+        Some(chain.iter().fold(value, |acc, layer| {
+            layer
+                .mappings
+                .iter()
+                .find_map(|map| map.translate(acc))
+                .unwrap_or(acc)
+        }))
+    }
+
+    /* e.g. sources("seed", "location", 82): all seeds ending at location 82 */
+    fn sources(&self, from: &str, to: &str, value: u64) -> Option<Vec<Range<u64>>> {
+        self.reverse(
+            from,
+            to,
+            vec![Range {
+                start: value,
+                end: value.saturating_add(1),
+            }],
+        )
+    }
+
+    /* layer maps from `from` to `to`, for walking backwards through them */
+    fn reverse_maps(&self, from: &str, to: &str) -> Option<Vec<PiecewiseMap>> {
+        let chain = self.chain(from, to)?;
+
+        Some(
+            chain
+                .iter()
+                .map(|layer| PiecewiseMap::from_layer(&layer.mappings))
+                .collect(),
+        )
+    }
+
+    /* all `from` values translating into `ranges` of `to`, one layer at a time
+     * (e.g. location -> humidity -> ... -> seed)
+     */
+    fn reverse(&self, from: &str, to: &str, ranges: Vec<Range<u64>>) -> Option<Vec<Range<u64>>> {
+        let maps = self.reverse_maps(from, to)?;
+
+        Some(reverse_through(&maps, ranges))
+    }
+}
+
+/* why the almanac could not be read: nom's own error, or a mapping row whose
+ * numbers do not make a valid range
+ */
+#[derive(Debug, PartialEq)]
+enum AlmanacError<'a> {
+    Syntax(nom::error::Error<&'a str>),
+    Range { line: usize, error: RangeError },
+}
+
+impl<'a> ParseError<&'a str> for AlmanacError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        AlmanacError::Syntax(nom::error::Error::new(input, kind))
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl std::fmt::Display for AlmanacError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AlmanacError::Syntax(error) => write!(f, "{error}"),
+            AlmanacError::Range { line, error } => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+/* 1-based line of `fragment`, which must be a slice of `source` */
+fn line_of(source: &str, fragment: &str) -> usize {
+    source[..source.offset(fragment)].matches('\n').count() + 1
+}
 
-    Ok((input, (mappings)))
+fn parse_row(input: &str) -> IResult<&str, (&str, [u64; 3]), AlmanacError<'_>> {
+    let (row, _) = multispace0(input)?;
+    let (input, (dst, _, src, _, len)) = tuple((u64, space1, u64, space1, u64))(row)?;
+
+    Ok((input, (row, [dst, src, len])))
+}
+
+fn parse_map<'a>(
+    source: &'a str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, Layer, AlmanacError<'a>> {
+    move |input| {
+        let (input, (_, from, _, to, _, rows)) = tuple((
+            multispace0,
+            alpha1,
+            tag("-to-"),
+            alpha1,
+            tag(" map:"),
+            many1(parse_row),
+        ))(input)?;
+
+        let mut mappings = Vec::new();
+        for (row, v) in rows {
+            let line = line_of(source, row);
+            let mapping = Mapping::new(v[0], v[1], v[2], line)
+                .map_err(|error| nom::Err::Failure(AlmanacError::Range { line, error }))?;
+            mappings.push(mapping);
+        }
+
+        Ok((
+            input,
+            Layer {
+                from: from.to_string(),
+                to: to.to_string(),
+                mappings,
+            },
+        ))
+    }
 }
 
-fn parse_input(input: &str) -> IResult<&str, (Vec<u64>, Vec<Vec<Mapping>>)> {
+fn parse_almanac(input: &str) -> IResult<&str, Almanac, AlmanacError<'_>> {
+    let source = input;
     let (input, (_, seeds)) = tuple((tag("seeds: "), separated_list1(multispace0, u64)))(input)?;
 
-    let (input, mappings) = many1(parse_map)(input)?;
+    let (input, layers) = many1(parse_map(source))(input)?;
 
-    Ok((input, (seeds, mappings)))
+    Ok((input, Almanac { seeds, layers }))
+}
+
+/* seeds and the layers from seed to location, in translation order */
+type SeedLayers = (Vec<u64>, Vec<Vec<Mapping>>);
+
+fn parse_input(input: &str) -> IResult<&str, SeedLayers, AlmanacError<'_>> {
+    let (rest, almanac) = parse_almanac(input)?;
+
+    let Some(chain) = almanac.chain("seed", "location") else {
+        return Err(nom::Err::Failure(AlmanacError::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        )));
+    };
+    let mappings = chain.iter().map(|layer| layer.mappings.clone()).collect();
+
+    Ok((rest, (almanac.seeds, mappings)))
 }
 
 fn part1(input: &str) -> u64 {
@@ -74,33 +731,66 @@ fn part1(input: &str) -> u64 {
 fn part2(input: &str) -> u64 {
     let (_input, (seeds, mappings)) = parse_input(input).expect("Unable to parse input");
 
-    let seeds_ranges: Vec<_> = seeds
-        .chunks(2)
-        .map(|v| Range {
-            start: v[0],
-            end: v[0] + v[1],
-        })
-        .collect();
+    let seeds_ranges = seed_ranges(&seeds).expect("Invalid seed ranges");
 
-    seeds_ranges
+    mappings
+        .iter()
+        .fold(seeds_ranges, |ranges, mapping| {
+            translate_ranges(mapping, ranges)
+        })
         .iter()
-        .map(|seed_mapping| {
-            seed_mapping
+        .map(|range| range.start)
+        .min()
+        .expect("There was an error finding out minimum")
+}
+
+/* locations checked at once while searching upwards, before narrowing down to
+ * the single lowest one
+ */
+const REVERSE_BLOCK: u64 = 1 << 16;
+
+fn part2_reverse(input: &str) -> u64 {
+    let (_input, almanac) = parse_almanac(input).expect("Unable to parse input");
+    let maps = almanac
+        .reverse_maps("seed", "location")
+        .expect("No way from seed to location");
+
+    let seeds = normalize(seed_ranges(&almanac.seeds).expect("Invalid seed ranges"));
+    let has_seed =
+        |locations: Range<u64>| intersects(&reverse_through(&maps, vec![locations]), &seeds);
+
+    let mut location = 0;
+    while location < u64::MAX {
+        let block = location..location.saturating_add(REVERSE_BLOCK);
+        if has_seed(block.clone()) {
+            return block
                 .clone()
-                .into_iter()
-                .map(|seed| {
-                    let result = mappings.iter().fold(seed, |acc, mapping| {
-                        for map in mapping {
-                            if let Some(result) = map.translate(acc) {
-                                return result;
-                            }
-                        }
-                        acc
-                    });
-                    result
-                })
-                .min()
-                .expect("There was an error finding out minimum")
+                .find(|location| has_seed(*location..location + 1))
+                .expect("Block with a seed has no single location with a seed");
+        }
+        location = block.end;
+    }
+
+    panic!("No location has a valid seed");
+}
+
+#[cfg(test)]
+fn part2_brute_force(input: &str) -> u64 {
+    let (_input, (seeds, mappings)) = parse_input(input).expect("Unable to parse input");
+
+    seed_ranges(&seeds)
+        .expect("Invalid seed ranges")
+        .into_iter()
+        .flatten()
+        .map(|seed| {
+            mappings.iter().fold(seed, |acc, mapping| {
+                for map in mapping {
+                    if let Some(result) = map.translate(acc) {
+                        return result;
+                    }
+                }
+                acc
+            })
         })
         .min()
         .expect("There was an error finding out minimum")
@@ -109,6 +799,87 @@ fn part2(input: &str) -> u64 {
 fn main() {
     let input = include_str!("../input.txt");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+
+    match args.as_slice() {
+        ["--translate", from, value, to] => {
+            let (_input, almanac) = parse_almanac(input).expect("Unable to parse input");
+            let value = value.parse::<u64>().expect("Value must be a number");
+            match almanac.translate(from, value, to) {
+                Some(result) => println!("{from} {value} is {to} {result}"),
+                None => println!("No way to get from {from} to {to}"),
+            }
+            return;
+        }
+        ["--sources", from, to, value] => {
+            let (_input, almanac) = parse_almanac(input).expect("Unable to parse input");
+            let value = value.parse::<u64>().expect("Value must be a number");
+            match almanac.sources(from, to, value) {
+                Some(ranges) => println!("{from} values ending at {to} {value}: {ranges:?}"),
+                None => println!("No way to get from {from} to {to}"),
+            }
+            return;
+        }
+        ["--render", format, path] => {
+            let (_input, almanac) = parse_almanac(input).expect("Unable to parse input");
+            let layers = almanac
+                .chain("seed", "location")
+                .expect("No way from seed to location");
+            let seeds = seed_ranges(&almanac.seeds).expect("Invalid seed ranges");
+            let out = match *format {
+                "svg" => render::render_svg(&seeds, &layers),
+                "html" => render::render_html(&seeds, &layers),
+                _ => panic!("Unknown render format {format}, expected svg or html"),
+            };
+            std::fs::write(path, out).expect("Unable to write render file");
+            println!("Interval flow written to {path}");
+            return;
+        }
+        _ => {}
+    }
+
+    if args.contains(&"--validate") {
+        let (_input, almanac) = parse_almanac(input).expect("Unable to parse input");
+        let issues = validate(&almanac);
+        for issue in issues.iter() {
+            println!("{issue}");
+        }
+        println!("{} issues found", issues.len());
+        return;
+    }
+
+    if args.contains(&"--reverse") {
+        let result = part2_reverse(input);
+        println!("Result part 2: {result}");
+        return;
+    }
+
+    if args.contains(&"--collapse") {
+        let (_input, (seeds, mappings)) = parse_input(input).expect("Unable to parse input");
+        let map = collapse(&mappings);
+
+        print!("Seed to location map:\n{map}");
+        match map.invert() {
+            Some(inverse) => println!(
+                "Invertible, inverse has {} segments",
+                inverse.segments.len()
+            ),
+            None => println!("Not invertible"),
+        }
+
+        let result = seeds.iter().map(|seed| map.eval(*seed)).min();
+        println!("Result part 1: {}", result.expect("No seeds"));
+        let result = seed_ranges(&seeds)
+            .expect("Invalid seed ranges")
+            .iter()
+            .flat_map(|range| map.eval_range(range))
+            .map(|range| range.start)
+            .min();
+        println!("Result part 2: {}", result.expect("No seeds"));
+        return;
+    }
+
     let result = part1(input);
     println!("Result part 1: {result}");
 
@@ -193,3 +964,462 @@ humidity-to-location map:
 ";
     assert_eq!(part2(input), 46);
 }
+
+#[test]
+fn test_part2_matches_brute_force() {
+    let input = "seeds: 79 14 55 13 0 5 97 10
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+    assert_eq!(part2(input), part2_brute_force(input));
+
+    /* overlapping mappings: the first one listed wins, as in part 1 */
+    let map = [
+        Mapping {
+            src: 10..20,
+            dst: 100..110,
+            ..Default::default()
+        },
+        Mapping {
+            src: 15..30,
+            dst: 200..215,
+            ..Default::default()
+        },
+    ];
+    let mut ranges = translate_ranges(&map, vec![Range { start: 5, end: 25 }]);
+    ranges.sort_by_key(|range| range.start);
+    assert_eq!(ranges, vec![5..10, 100..110, 205..210]);
+}
+
+#[test]
+fn test_piecewise_map() {
+    let input = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+    let (_, (seeds, mappings)) = parse_input(input).unwrap();
+    let map = collapse(&mappings);
+
+    let locations: Vec<u64> = seeds.iter().map(|seed| map.eval(*seed)).collect();
+    assert_eq!(locations, vec![82, 43, 86, 35]);
+
+    /* every seed in 0..120 agrees with the layer-by-layer fold */
+    for seed in 0..120 {
+        let expected = mappings.iter().fold(seed, |acc, layer| {
+            layer
+                .iter()
+                .find_map(|map| map.translate(acc))
+                .unwrap_or(acc)
+        });
+        assert_eq!(map.eval(seed), expected);
+    }
+
+    let mut ranges = map.eval_range(&(79..93));
+    ranges.sort_by_key(|range| range.start);
+    let mut expected = translate_ranges(&mappings[0], vec![Range { start: 79, end: 93 }]);
+    for layer in mappings[1..].iter() {
+        expected = translate_ranges(layer, expected);
+    }
+    expected.sort_by_key(|range| range.start);
+    assert_eq!(
+        ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum::<u64>(),
+        14
+    );
+    assert_eq!(
+        ranges.first().map(|range| range.start),
+        expected.first().map(|range| range.start)
+    );
+
+    /* all example layers are permutations of their domain, so the whole chain inverts */
+    let inverse = map.invert().expect("Example almanac is bijective");
+    for seed in seeds {
+        assert_eq!(inverse.eval(map.eval(seed)), seed);
+    }
+    assert_eq!(map.compose(&inverse).simplify(), PiecewiseMap::identity());
+}
+
+#[test]
+fn test_piecewise_map_not_invertible() {
+    /* 10..20 lands on 30..40, which identity already maps onto itself */
+    let map = PiecewiseMap::from_layer(&[Mapping {
+        src: 10..20,
+        dst: 30..40,
+        ..Default::default()
+    }]);
+    assert!(map.invert().is_none());
+    assert_eq!(map.eval(15), 35);
+    assert_eq!(map.eval(35), 35);
+    assert_eq!(map.segments.len(), 3);
+}
+
+#[test]
+fn test_category_queries() {
+    /* layers shuffled, the category names decide the order */
+    let input = "seeds: 79 14 55 13
+
+humidity-to-location map:
+60 56 37
+56 93 4
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+water-to-light map:
+88 18 7
+18 25 70
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+";
+    assert_eq!(part1(input), 35);
+    assert_eq!(part2(input), 46);
+
+    let (_, almanac) = parse_almanac(input).unwrap();
+    assert_eq!(almanac.translate("soil", 81, "humidity"), Some(78));
+    assert_eq!(almanac.translate("seed", 79, "location"), Some(82));
+    assert_eq!(almanac.translate("location", 82, "seed"), None);
+
+    let seeds = almanac.sources("seed", "location", 82).unwrap();
+    assert!(seeds.iter().any(|range| range.contains(&79)));
+    for range in seeds {
+        for seed in range {
+            assert_eq!(almanac.translate("seed", seed, "location"), Some(82));
+        }
+    }
+}
+
+#[test]
+fn test_reverse_lookup() {
+    let input = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+    assert_eq!(part2_reverse(input), 46);
+
+    let (_, almanac) = parse_almanac(input).unwrap();
+    let seeds = almanac
+        .reverse("seed", "location", vec![Range { start: 40, end: 50 }])
+        .unwrap();
+    assert!(seeds.iter().any(|range| range.contains(&82)));
+    assert_eq!(
+        seeds
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum::<u64>(),
+        10
+    );
+    for seed in seeds.into_iter().flatten() {
+        let location = almanac.translate("seed", seed, "location").unwrap();
+        assert!((40..50).contains(&location));
+    }
+
+    let humidity = almanac
+        .reverse("humidity", "location", vec![Range { start: 46, end: 47 }])
+        .unwrap();
+    assert_eq!(humidity, vec![Range { start: 46, end: 47 }]);
+}
+
+#[test]
+fn test_validate() {
+    let input = "seeds: 1 2
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 10 10
+100 15 10
+5 30 5
+200 40 5
+";
+    let (_, almanac) = parse_almanac(input).unwrap();
+
+    assert_eq!(almanac.layers[1].mappings[2].line, 10);
+    assert_eq!(
+        validate(&almanac),
+        vec![
+            Issue::SourceOverlap {
+                layer: "soil-to-fertilizer".to_string(),
+                lines: (8, 9),
+                range: 15..20,
+            },
+            Issue::Gap {
+                layer: "soil-to-fertilizer".to_string(),
+                lines: (9, 10),
+                range: 25..30,
+            },
+            Issue::Gap {
+                layer: "soil-to-fertilizer".to_string(),
+                lines: (10, 11),
+                range: 35..40,
+            },
+            Issue::DestinationOverlap {
+                layer: "soil-to-fertilizer".to_string(),
+                lines: (8, 10),
+                range: 5..10,
+            },
+        ]
+    );
+    assert_eq!(
+        validate(&almanac)[0].to_string(),
+        "lines 8 and 9: soil-to-fertilizer source ranges overlap on 15..20"
+    );
+
+    /* gaps name the lines in file order, not value order */
+    let (_, almanac) =
+        parse_almanac("seeds: 1 2\n\nseed-to-soil map:\n100 40 5\n0 10 10\n").unwrap();
+    assert_eq!(
+        validate(&almanac),
+        vec![Issue::Gap {
+            layer: "seed-to-soil".to_string(),
+            lines: (4, 5),
+            range: 20..40,
+        }]
+    );
+}
+
+#[test]
+fn test_checked_ranges() {
+    assert_eq!(checked_range(5, 3), Ok(5..8));
+    assert_eq!(checked_range(u64::MAX - 3, 3), Ok(u64::MAX - 3..u64::MAX));
+    assert_eq!(
+        checked_range(u64::MAX - 3, 4),
+        Err(RangeError::Overflow {
+            start: u64::MAX - 3,
+            len: 4
+        })
+    );
+    assert_eq!(checked_range(7, 0), Err(RangeError::Empty { start: 7 }));
+
+    assert_eq!(seed_ranges(&[79, 14, 55, 13]), Ok(vec![79..93, 55..68]));
+    assert_eq!(seed_ranges(&[79, 14, 55]), Err(RangeError::OddSeedCount(3)));
+    assert_eq!(seed_ranges(&[79, 0]), Err(RangeError::Empty { start: 79 }));
+    assert_eq!(
+        RangeError::OddSeedCount(3).to_string(),
+        "3 seed values can not be paired into ranges"
+    );
+
+    /* a mapping reaching the top of the u64 range still translates and
+     * composes, values it does not cover pass through
+     */
+    let input = "seeds: 18446744073709551610 5 3 2
+
+seed-to-soil map:
+0 10 18446744073709551605
+
+soil-to-location map:
+100 0 10
+";
+    let (_, (seeds, mappings)) = parse_input(input).unwrap();
+    assert_eq!(mappings[0][0].src, 10..u64::MAX);
+    assert_eq!(mappings[0][0].translate(u64::MAX - 1), Some(u64::MAX - 11));
+    assert_eq!(part1(input), 102);
+    assert_eq!(part2(input), 103);
+    assert_eq!(part2_brute_force(input), 103);
+    assert_eq!(collapse(&mappings).eval(seeds[0]), u64::MAX - 15);
+
+    /* a row running past u64::MAX is rejected where it stands */
+    let input = "seeds: 1 2
+
+seed-to-location map:
+0 10 18446744073709551606
+";
+    let Err(nom::Err::Failure(error)) = parse_almanac(input) else {
+        panic!("expected a failure");
+    };
+    assert_eq!(
+        error,
+        AlmanacError::Range {
+            line: 4,
+            error: RangeError::Overflow {
+                start: 10,
+                len: 18446744073709551606,
+            },
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "line 4: range 10 of length 18446744073709551606 ends past 18446744073709551615"
+    );
+
+    let input = "seeds: 1 2\n\nseed-to-soil map:\n0 10 5\n\nsoil-to-location map:\n3 7 1\n0 10 0\n";
+    assert_eq!(
+        parse_almanac(input).unwrap_err(),
+        nom::Err::Failure(AlmanacError::Range {
+            line: 8,
+            error: RangeError::Empty { start: 10 },
+        })
+    );
+}
+
+#[test]
+fn test_render() {
+    let input = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
+    let (_, almanac) = parse_almanac(input).unwrap();
+    let layers = almanac.chain("seed", "location").unwrap();
+    let seeds = seed_ranges(&almanac.seeds).unwrap();
+
+    let svg = render::render_svg(&seeds, &layers);
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains(">seed</text>"));
+    assert!(svg.contains(">location</text>"));
+    assert!(svg.contains("<title>seed 55..68</title>"));
+    assert!(svg.contains("<title>location 46..56</title>"));
+    assert!(svg.contains("lowest location 46"));
+
+    /* one highlighted interval per category, from seed to location */
+    assert_eq!(svg.matches("class=\"node min\"").count(), 8);
+    assert!(svg.contains("<title>seed 79..93</title>"));
+    assert!(svg.contains("<path class=\"min\""));
+    assert!(svg.contains("<title>line 5: 79..93 to 81..95</title>"));
+    assert!(svg.contains("<title>81..95 unchanged</title>"));
+
+    let html = render::render_html(&seeds, &layers);
+    assert!(html.contains("<title>Almanac interval flow</title>"));
+    assert!(html.contains(&svg));
+}
@@ -0,0 +1,276 @@
+use std::ops::Range;
+
+use crate::Layer;
+
+const COLUMN_WIDTH: f64 = 200.0;
+const NODE_WIDTH: f64 = 12.0;
+/* height shared by all intervals of one column, before gaps are added */
+const HEIGHT: f64 = 600.0;
+const GAP: f64 = 4.0;
+const MARGIN: f64 = 40.0;
+
+/* one interval in a column of the diagram */
+#[derive(Debug, Clone)]
+struct Node {
+    range: Range<u64>,
+    /* index into the previous column, and the part of that interval that ended up here */
+    parent: Option<(usize, Range<u64>)>,
+    /* line of the mapping that moved it here, None if it passed through unchanged */
+    line: Option<usize>,
+    y: f64,
+    height: f64,
+    minimum: bool,
+}
+
+/* intervals of every category from seed to location, each column sorted by value */
+struct Flow {
+    names: Vec<String>,
+    columns: Vec<Vec<Node>>,
+    scale: f64,
+}
+
+/* split `range` the way `translate_ranges` does, keeping where every piece came from */
+fn split(layer: &Layer, range: &Range<u64>) -> Vec<(Range<u64>, Range<u64>, Option<usize>)> {
+    let mut pending = vec![range.clone()];
+    let mut pieces = Vec::new();
+
+    for map in layer.mappings.iter() {
+        let mut rest = Vec::new();
+        for range in pending {
+            let (translated, untouched) = map.translate_range(&range);
+            if let Some(dst) = translated {
+                let start = range.start.max(map.src.start);
+                pieces.push((start..start + (dst.end - dst.start), dst, Some(map.line)));
+            }
+            rest.extend(untouched);
+        }
+        pending = rest;
+    }
+    pieces.extend(
+        pending
+            .into_iter()
+            .map(|range| (range.clone(), range, None)),
+    );
+
+    pieces
+}
+
+impl Flow {
+    fn new(seeds: &[Range<u64>], layers: &[&Layer]) -> Flow {
+        let node = |range, parent, line| Node {
+            range,
+            parent,
+            line,
+            y: 0.0,
+            height: 0.0,
+            minimum: false,
+        };
+
+        let mut first: Vec<_> = seeds
+            .iter()
+            .map(|range| node(range.clone(), None, None))
+            .collect();
+        first.sort_by_key(|node| node.range.start);
+
+        let mut columns = vec![first];
+        for layer in layers {
+            let mut next = Vec::new();
+            for (idx, parent) in columns[columns.len() - 1].iter().enumerate() {
+                for (src, dst, line) in split(layer, &parent.range) {
+                    next.push(node(dst, Some((idx, src)), line));
+                }
+            }
+            next.sort_by_key(|node| node.range.start);
+            columns.push(next);
+        }
+
+        let mut names: Vec<String> = layers.iter().map(|layer| layer.from.clone()).collect();
+        names.extend(layers.last().map(|layer| layer.to.clone()));
+
+        let total: u64 = seeds.iter().map(|range| range.end - range.start).sum();
+        let mut flow = Flow {
+            names,
+            columns,
+            scale: HEIGHT / total.max(1) as f64,
+        };
+        flow.layout();
+        flow.mark_minimum();
+
+        flow
+    }
+
+    fn height_of(&self, range: &Range<u64>) -> f64 {
+        ((range.end - range.start) as f64 * self.scale).max(1.0)
+    }
+
+    fn layout(&mut self) {
+        for col in 0..self.columns.len() {
+            let mut y = MARGIN;
+            for idx in 0..self.columns[col].len() {
+                let height = self.height_of(&self.columns[col][idx].range);
+                let node = &mut self.columns[col][idx];
+                node.y = y;
+                node.height = height;
+                y += height + GAP;
+            }
+        }
+    }
+
+    /* follow the lowest location back to the seeds it came from */
+    fn mark_minimum(&mut self) {
+        let Some(last) = self.columns.last() else {
+            return;
+        };
+        let mut current = (0..last.len()).min_by_key(|idx| last[*idx].range.start);
+
+        for col in (0..self.columns.len()).rev() {
+            let Some(idx) = current else {
+                break;
+            };
+            let node = &mut self.columns[col][idx];
+            node.minimum = true;
+            current = node.parent.as_ref().map(|(parent, _)| *parent);
+        }
+    }
+
+    fn minimum(&self) -> Option<u64> {
+        self.columns
+            .last()
+            .and_then(|column| column.iter().map(|node| node.range.start).min())
+    }
+
+    fn width(&self) -> f64 {
+        MARGIN * 2.0 + COLUMN_WIDTH * (self.columns.len().max(1) - 1) as f64 + NODE_WIDTH
+    }
+
+    fn height(&self) -> f64 {
+        let bottom = self
+            .columns
+            .iter()
+            .flat_map(|column| column.last())
+            .map(|node| node.y + node.height)
+            .fold(MARGIN, f64::max);
+
+        bottom + MARGIN
+    }
+}
+
+fn x_of(col: usize) -> f64 {
+    MARGIN + COLUMN_WIDTH * col as f64
+}
+
+pub fn render_svg(seeds: &[Range<u64>], layers: &[&Layer]) -> String {
+    let flow = Flow::new(seeds, layers);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">
+<style>
+text {{ font-family: monospace; font-size: 12px; fill: #cccccc; }}
+.node {{ fill: #5577aa; }}
+.moved {{ fill: #55cccc; fill-opacity: 0.35; }}
+.passed {{ fill: #888888; fill-opacity: 0.25; }}
+.min {{ fill: #ffff66; fill-opacity: 0.9; }}
+</style>
+<rect width=\"100%\" height=\"100%\" fill=\"#0f0f23\"/>
+",
+        flow.width(),
+        flow.height()
+    );
+
+    for (col, name) in flow.names.iter().enumerate() {
+        out.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\">{name}</text>\n",
+            x_of(col),
+            MARGIN / 2.0
+        ));
+    }
+
+    for col in 1..flow.columns.len() {
+        let (x0, x1) = (x_of(col - 1) + NODE_WIDTH, x_of(col));
+        let xm = (x0 + x1) / 2.0;
+
+        for node in flow.columns[col].iter() {
+            let Some((idx, src)) = &node.parent else {
+                continue;
+            };
+            let parent = &flow.columns[col - 1][*idx];
+            let y0 = parent.y + (src.start - parent.range.start) as f64 * flow.scale;
+            let (y1, h) = (node.y, node.height);
+            let class = match (node.minimum, node.line) {
+                (true, _) => "min",
+                (false, Some(_)) => "moved",
+                (false, None) => "passed",
+            };
+            let title = match node.line {
+                Some(line) => format!(
+                    "line {line}: {}..{} to {}..{}",
+                    src.start, src.end, node.range.start, node.range.end
+                ),
+                None => format!("{}..{} unchanged", src.start, src.end),
+            };
+
+            out.push_str(&format!(
+                "<path class=\"{class}\" d=\"M{x0:.1},{y0:.1} C{xm:.1},{y0:.1} {xm:.1},{y1:.1} {x1:.1},{y1:.1} L{x1:.1},{:.1} C{xm:.1},{:.1} {xm:.1},{:.1} {x0:.1},{:.1} Z\"><title>{title}</title></path>\n",
+                y1 + h,
+                y1 + h,
+                y0 + h,
+                y0 + h
+            ));
+        }
+    }
+
+    for (col, column) in flow.columns.iter().enumerate() {
+        for node in column {
+            let class = if node.minimum { "node min" } else { "node" };
+            out.push_str(&format!(
+                "<rect class=\"{class}\" x=\"{:.1}\" y=\"{:.1}\" width=\"{NODE_WIDTH:.1}\" height=\"{:.1}\"><title>{} {}..{}</title></rect>\n",
+                x_of(col),
+                node.y,
+                node.height,
+                flow.names[col],
+                node.range.start,
+                node.range.end
+            ));
+        }
+    }
+
+    if let Some(minimum) = flow.minimum() {
+        out.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\">lowest location {minimum}</text>\n",
+            x_of(flow.columns.len() - 1) - COLUMN_WIDTH / 2.0,
+            flow.height() - MARGIN / 2.0
+        ));
+    }
+
+    out.push_str("</svg>\n");
+
+    out
+}
+
+pub fn render_html(seeds: &[Range<u64>], layers: &[&Layer]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Almanac interval flow</title>
+<style>
+body { background: #0f0f23; color: #cccccc; font-family: monospace; }
+.moved { color: #55cccc; }
+.passed { color: #888888; }
+.min { color: #ffff66; font-weight: bold; }
+</style>
+</head>
+<body>
+<p>
+<span class=\"moved\">moved by a mapping</span>
+<span class=\"passed\">passed through unchanged</span>
+<span class=\"min\">path to the lowest location</span>
+</p>
+",
+    );
+
+    out.push_str(&render_svg(seeds, layers));
+    out.push_str("</body>\n</html>\n");
+
+    out
+}
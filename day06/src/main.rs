@@ -1,120 +1,844 @@
 use nom::{
-    bytes::complete::tag,
-    character::complete::{digit1, multispace0, newline, space1, u64},
-    multi::separated_list1,
-    sequence::tuple,
+    branch::alt,
+    bytes::complete::{tag, take_while1},
+    character::complete::{line_ending, space0, space1},
+    combinator::eof,
+    multi::{many1, separated_list1},
+    sequence::{delimited, terminated, tuple},
     IResult,
 };
+use num_bigint::BigUint;
+use std::ops::RangeInclusive;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Race {
     time: u64,
     distance: u64,
 }
 
-fn parse_part1(input: &str) -> IResult<&str, Vec<Race>> {
-    let (input, (_, _, times, _)) = tuple((
-        tag("Time:"),
-        multispace0,
-        separated_list1(space1, u64),
-        newline,
-    ))(input)?;
-    let (input, (_, _, distances, _)) = tuple((
-        tag("Distance:"),
-        multispace0,
-        separated_list1(space1, u64),
-        newline,
-    ))(input)?;
+/* one labelled line of the sheet, columns kept as written */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row<'a> {
+    label: &'a str,
+    columns: Vec<&'a str>,
+}
 
-    let races = times
-        .iter()
-        .zip(distances.iter())
-        .map(|v| Race {
-            time: *v.0,
-            distance: *v.1,
+/* the race sheet before deciding how to read it, Time and Distance plus any
+ * extra rows such as record holders
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Sheet<'a> {
+    rows: Vec<Row<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SheetError {
+    MissingRow(&'static str),
+    ColumnCount {
+        label: String,
+        expected: usize,
+        found: usize,
+    },
+    NotANumber {
+        label: String,
+        value: String,
+    },
+    /* digits only, but more than a u64 holds */
+    TooLarge {
+        label: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SheetError::MissingRow(label) => write!(f, "no {label} row"),
+            SheetError::ColumnCount {
+                label,
+                expected,
+                found,
+            } => write!(f, "{label} row has {found} columns, expected {expected}"),
+            SheetError::NotANumber { label, value } => {
+                write!(f, "{label} row has {value:?}, which is not a number")
+            }
+            SheetError::TooLarge { label, value } => {
+                write!(f, "{label} row has {value}, which does not fit in 64 bits")
+            }
+        }
+    }
+}
+
+impl<'a> Sheet<'a> {
+    fn row(&self, label: &str) -> Option<&Row<'a>> {
+        self.rows.iter().find(|row| row.label == label)
+    }
+
+    /* rows other than Time and Distance */
+    fn extra_rows(&self) -> impl Iterator<Item = &Row<'a>> {
+        self.rows
+            .iter()
+            .filter(|row| row.label != "Time" && row.label != "Distance")
+    }
+
+    /* every row needs a value for every race */
+    fn check(&self) -> Result<(), SheetError> {
+        let expected = self
+            .row("Time")
+            .ok_or(SheetError::MissingRow("Time"))?
+            .columns
+            .len();
+        self.row("Distance")
+            .ok_or(SheetError::MissingRow("Distance"))?;
+
+        match self.rows.iter().find(|row| row.columns.len() != expected) {
+            Some(row) => Err(SheetError::ColumnCount {
+                label: row.label.to_string(),
+                expected,
+                found: row.columns.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn numbers(&self, label: &'static str) -> Result<Vec<u64>, SheetError> {
+        let row = self.row(label).ok_or(SheetError::MissingRow(label))?;
+
+        row.columns
+            .iter()
+            .map(|value| parse_number(label, value))
+            .collect()
+    }
+
+    /* the digits of every column read together as one number */
+    fn kerned_number(&self, label: &'static str) -> Result<u64, SheetError> {
+        let row = self.row(label).ok_or(SheetError::MissingRow(label))?;
+
+        parse_number(label, &row.columns.concat())
+    }
+
+    /* every column is its own race */
+    fn races(&self) -> Result<Vec<Race>, SheetError> {
+        self.check()?;
+
+        let races = self
+            .numbers("Time")?
+            .into_iter()
+            .zip(self.numbers("Distance")?)
+            .map(|(time, distance)| Race { time, distance })
+            .collect();
+
+        Ok(races)
+    }
+
+    /* the spaces between columns are bad kerning, it is one long race */
+    fn kerned(&self) -> Result<Race, SheetError> {
+        self.check()?;
+
+        Ok(Race {
+            time: self.kerned_number("Time")?,
+            distance: self.kerned_number("Distance")?,
         })
-        .collect();
+    }
+
+    fn kerned_big_number(&self, label: &'static str) -> Result<BigUint, SheetError> {
+        let row = self.row(label).ok_or(SheetError::MissingRow(label))?;
+        let value = row.columns.concat();
 
-    Ok((input, races))
+        BigUint::parse_bytes(value.as_bytes(), 10).ok_or(SheetError::NotANumber {
+            label: label.to_string(),
+            value,
+        })
+    }
+
+    /* the kerned race, as a plain u64 race while its numbers fit */
+    fn kerned_any(&self) -> Result<KernedRace, SheetError> {
+        match self.kerned() {
+            Ok(race) => Ok(KernedRace::Small(race)),
+            Err(SheetError::TooLarge { .. }) => Ok(KernedRace::Big(BigRace {
+                time: self.kerned_big_number("Time")?,
+                distance: self.kerned_big_number("Distance")?,
+            })),
+            Err(err) => Err(err),
+        }
+    }
 }
 
-fn parse_part2(input: &str) -> IResult<&str, Race> {
-    let (input, (_, _, times, _)) = tuple((
-        tag("Time:"),
-        multispace0,
-        separated_list1(space1, digit1),
-        newline,
-    ))(input)?;
-    let (input, (_, _, distances, _)) = tuple((
-        tag("Distance:"),
-        multispace0,
-        separated_list1(space1, digit1),
-        newline,
+fn parse_number(label: &str, value: &str) -> Result<u64, SheetError> {
+    value.parse::<u64>().map_err(|err| match err.kind() {
+        std::num::IntErrorKind::PosOverflow => SheetError::TooLarge {
+            label: label.to_string(),
+            value: value.to_string(),
+        },
+        _ => SheetError::NotANumber {
+            label: label.to_string(),
+            value: value.to_string(),
+        },
+    })
+}
+
+/* a race whose numbers do not fit in a u64 */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BigRace {
+    time: BigUint,
+    distance: BigUint,
+}
+
+impl From<&Race> for BigRace {
+    fn from(race: &Race) -> Self {
+        BigRace {
+            time: BigUint::from(race.time),
+            distance: BigUint::from(race.distance),
+        }
+    }
+}
+
+/* hold times never exceed the race time, which came from a u64 */
+fn hold_u64(hold: &BigUint) -> u64 {
+    u64::try_from(hold).expect("Hold time does not fit in a u64")
+}
+
+impl BigRace {
+    /* same as `Race::winning`, BigUint::sqrt is exact like `isqrt` */
+    fn winning(&self) -> Option<RangeInclusive<BigUint>> {
+        let (time, distance) = (&self.time, &self.distance);
+        let square = time * time;
+        let record = distance * 4u32;
+        if square < record {
+            return None;
+        }
+
+        let half = time / 2u32;
+        let mut first = (time - (square - record).sqrt()) / 2u32;
+        while first <= half && &first * (time - &first) <= *distance {
+            first += 1u32;
+        }
+        if first > half {
+            return None;
+        }
+
+        let last = time - &first;
+        Some(first..=last)
+    }
+
+    #[cfg(test)]
+    fn ways(&self) -> BigUint {
+        self.winning().map_or(BigUint::from(0u32), |range| {
+            range.end() - range.start() + 1u32
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KernedRace {
+    Small(Race),
+    Big(BigRace),
+}
+
+impl KernedRace {
+    #[cfg(test)]
+    fn ways(&self) -> Ways {
+        match self {
+            KernedRace::Small(race) => Ways::Small(race.ways()),
+            KernedRace::Big(race) => Ways::from(race.ways()),
+        }
+    }
+
+    fn ways_with_boat(&self, boat: &Boat) -> Ways {
+        match self {
+            KernedRace::Small(race) => Ways::Small(boat.ways(race)),
+            KernedRace::Big(race) => Ways::from(boat.big_ways(race)),
+        }
+    }
+}
+
+impl std::fmt::Display for KernedRace {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KernedRace::Small(race) => write!(f, "{} ms, record {} mm", race.time, race.distance),
+            KernedRace::Big(race) => write!(f, "{} ms, record {} mm", race.time, race.distance),
+        }
+    }
+}
+
+/* number of winning hold times, a plain u64 unless the race is too long */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Ways {
+    Small(u64),
+    Big(BigUint),
+}
+
+impl From<BigUint> for Ways {
+    fn from(value: BigUint) -> Self {
+        match u64::try_from(&value) {
+            Ok(value) => Ways::Small(value),
+            Err(_) => Ways::Big(value),
+        }
+    }
+}
+
+impl PartialEq<u64> for Ways {
+    fn eq(&self, other: &u64) -> bool {
+        match self {
+            Ways::Small(value) => value == other,
+            Ways::Big(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Ways {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Ways::Small(value) => write!(f, "{value}"),
+            Ways::Big(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+fn parse_row(input: &str) -> IResult<&str, Row<'_>> {
+    let (input, (label, _, columns)) = tuple((
+        take_while1(|c: char| c != ':' && c != '\n'),
+        tag(":"),
+        delimited(
+            space0,
+            separated_list1(space1, take_while1(|c: char| !c.is_whitespace())),
+            space0,
+        ),
     ))(input)?;
 
-    let time = times
-        .into_iter()
-        .collect::<String>()
-        .parse::<u64>()
-        .expect("Unable to convert number");
+    Ok((
+        input,
+        Row {
+            label: label.trim(),
+            columns,
+        },
+    ))
+}
 
-    let distance = distances
-        .into_iter()
-        .collect::<String>()
-        .parse::<u64>()
-        .expect("Unable to convert number");
+fn parse_sheet(input: &str) -> IResult<&str, Sheet<'_>> {
+    let (input, rows) = many1(terminated(parse_row, alt((line_ending, eof))))(input)?;
 
-    Ok((input, Race {
-        time,
-        distance,
-    }))
+    Ok((input, Sheet { rows }))
 }
 
-fn calc_distance(time_pressed: u64, time_total: u64) -> u64 {
+fn calc_distance(time_pressed: u64, time_total: u64) -> u128 {
     let time_left = time_total - time_pressed;
 
-    time_left * time_pressed
+    time_left as u128 * time_pressed as u128
 }
 
-fn part1(input: &str) -> u64 {
-    let (_, races) = parse_part1(input).unwrap();
+/* largest r with r * r <= n */
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
 
-    races
-        .iter()
-        .map(|race| {
-            (0..race.time)
-                .map(|v| {
-                    if calc_distance(v, race.time) > race.distance {
-                        1
-                    } else {
-                        0
-                    }
-                })
-                .sum::<u64>()
+    /* Newton's method from above, it only ever decreases until it settles */
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (x + n / x) / 2;
+        if next >= x {
+            return x;
+        }
+        x = next;
+    }
+}
+
+impl Race {
+    /* hold times that beat the record, the solutions of t * (T - t) > D */
+    fn winning(&self) -> Option<RangeInclusive<u64>> {
+        let time = self.time as u128;
+        let discriminant = (time * time).checked_sub(4 * self.distance as u128)?;
+
+        /* (T - floor(sqrt(disc))) / 2 is at most one below the first winning
+         * hold time, so step up past the record exactly
+         */
+        let mut first = ((time - isqrt(discriminant)) / 2) as u64;
+        while first <= self.time / 2 && calc_distance(first, self.time) <= self.distance as u128 {
+            first += 1;
+        }
+        if first > self.time / 2 {
+            return None;
+        }
+
+        /* the distance is symmetric around T / 2 */
+        Some(first..=self.time - first)
+    }
+
+    #[cfg(test)]
+    fn ways(&self) -> u64 {
+        self.winning()
+            .map_or(0, |range| range.end() - range.start() + 1)
+    }
+}
+
+/* how a boat turns time spent holding the button into speed */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Boat {
+    /* speed gained per ms held */
+    acceleration: u64,
+    initial_speed: u64,
+    max_speed: Option<u64>,
+    min_hold: u64,
+    max_hold: Option<u64>,
+}
+
+impl Default for Boat {
+    fn default() -> Self {
+        Boat {
+            acceleration: 1,
+            initial_speed: 0,
+            max_speed: None,
+            min_hold: 0,
+            max_hold: None,
+        }
+    }
+}
+
+impl Boat {
+    fn speed(&self, time_pressed: u64) -> Option<u128> {
+        let speed = (self.acceleration as u128 * time_pressed as u128)
+            .checked_add(self.initial_speed as u128)?;
+        match self.max_speed {
+            Some(max) => Some(speed.min(max as u128)),
+            None => Some(speed),
+        }
+    }
+
+    fn distance(&self, time_pressed: u64, time_total: u64) -> Option<u128> {
+        self.speed(time_pressed)?
+            .checked_mul((time_total - time_pressed) as u128)
+    }
+
+    /* speed only grows with the hold time, so if the top speed times the whole
+     * race fits in a u128 every distance in it does
+     */
+    fn fits(&self, time: u64) -> bool {
+        self.speed(time)
+            .and_then(|speed| speed.checked_mul(time as u128))
+            .is_some()
+    }
+
+    /* hold times allowed in a race of `time` ms */
+    fn holds(&self, time: u64) -> Option<RangeInclusive<u64>> {
+        let last = self.max_hold.map_or(time, |max| max.min(time));
+        (self.min_hold <= last).then_some(self.min_hold..=last)
+    }
+
+    /* distance is the minimum of two concave functions of the hold time, so it
+     * rises to a single peak and falls after it; binary search both edges
+     */
+    fn winning(&self, race: &Race) -> Option<RangeInclusive<u64>> {
+        if *self == Boat::default() {
+            return race.winning();
+        }
+
+        if !self.fits(race.time) {
+            let range = self.big_winning(&BigRace::from(race))?;
+            return Some(hold_u64(range.start())..=hold_u64(range.end()));
+        }
+
+        let holds = self.holds(race.time)?;
+        let (low, high) = (*holds.start(), *holds.end());
+        let distance = |hold: u64| {
+            self.distance(hold, race.time)
+                .expect("Distances were checked to fit")
+        };
+        let beats = |hold: u64| distance(hold) > race.distance as u128;
+
+        let peak = self.peak(race)?;
+        if !beats(peak) {
+            return None;
+        }
+
+        let first = partition_point(low, peak, |hold| !beats(hold));
+        let last = partition_point(peak, high, |hold| hold < high && beats(hold + 1));
+
+        Some(first..=last)
+    }
+
+    /* first hold time after which the distance stops growing */
+    fn peak(&self, race: &Race) -> Option<u64> {
+        if *self == Boat::default() {
+            return Some(race.time / 2);
+        }
+
+        if !self.fits(race.time) {
+            return self
+                .big_peak(&BigRace::from(race))
+                .map(|hold| hold_u64(&hold));
+        }
+
+        let holds = self.holds(race.time)?;
+        let (low, high) = (*holds.start(), *holds.end());
+        let distance = |hold: u64| {
+            self.distance(hold, race.time)
+                .expect("Distances were checked to fit")
+        };
+
+        Some(partition_point(low, high, |hold| {
+            hold < high && distance(hold) < distance(hold + 1)
+        }))
+    }
+
+    fn outcome(&self, race: &Race) -> Outcome {
+        let winning = self.winning(race);
+        let best_hold = self.peak(race);
+        let best_distance = best_hold.map(|hold| match self.distance(hold, race.time) {
+            Some(distance) => BigUint::from(distance),
+            None => self.big_distance(&BigUint::from(hold), &BigUint::from(race.time)),
+        });
+
+        Outcome {
+            time: race.time,
+            record: race.distance,
+            ways: winning
+                .as_ref()
+                .map_or(0, |range| range.end() - range.start() + 1),
+            winning,
+            best_hold,
+            best_distance,
+        }
+    }
+
+    fn ways(&self, race: &Race) -> u64 {
+        self.winning(race)
+            .map_or(0, |range| range.end() - range.start() + 1)
+    }
+
+    fn big_distance(&self, time_pressed: &BigUint, time_total: &BigUint) -> BigUint {
+        let speed =
+            BigUint::from(self.initial_speed) + BigUint::from(self.acceleration) * time_pressed;
+        let speed = match self.max_speed {
+            Some(max) => speed.min(BigUint::from(max)),
+            None => speed,
+        };
+
+        speed * (time_total - time_pressed)
+    }
+
+    fn big_holds(&self, time: &BigUint) -> Option<(BigUint, BigUint)> {
+        let low = BigUint::from(self.min_hold);
+        let high = match self.max_hold {
+            Some(max) => time.clone().min(BigUint::from(max)),
+            None => time.clone(),
+        };
+
+        (low <= high).then_some((low, high))
+    }
+
+    /* same search as `peak`, for distances past u128 */
+    fn big_peak(&self, race: &BigRace) -> Option<BigUint> {
+        if *self == Boat::default() {
+            return Some(&race.time / 2u32);
+        }
+
+        let (low, high) = self.big_holds(&race.time)?;
+        let distance = |hold: &BigUint| self.big_distance(hold, &race.time);
+
+        Some(big_partition_point(&low, &high, |hold| {
+            *hold < high && distance(hold) < distance(&(hold + 1u32))
+        }))
+    }
+
+    /* same search as `winning`, for distances past u128 */
+    fn big_winning(&self, race: &BigRace) -> Option<RangeInclusive<BigUint>> {
+        if *self == Boat::default() {
+            return race.winning();
+        }
+
+        let (low, high) = self.big_holds(&race.time)?;
+        let beats = |hold: &BigUint| self.big_distance(hold, &race.time) > race.distance;
+
+        let peak = self.big_peak(race)?;
+        if !beats(&peak) {
+            return None;
+        }
+
+        let first = big_partition_point(&low, &peak, |hold| !beats(hold));
+        let last = big_partition_point(&peak, &high, |hold| *hold < high && beats(&(hold + 1u32)));
+
+        Some(first..=last)
+    }
+
+    fn big_ways(&self, race: &BigRace) -> BigUint {
+        self.big_winning(race).map_or(BigUint::from(0u32), |range| {
+            range.end() - range.start() + 1u32
         })
-        .product::<u64>()
+    }
 }
 
-fn part2(input: &str) -> u64 {
-    let (_, race) = parse_part2(input).unwrap();
+/* what the best strategy gets out of one race */
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Outcome {
+    time: u64,
+    record: u64,
+    ways: u64,
+    winning: Option<RangeInclusive<u64>>,
+    /* None when the boat may not hold the button for any allowed time */
+    best_hold: Option<u64>,
+    best_distance: Option<BigUint>,
+}
 
-    (0..race.time)
-        .map(|v| {
-            if calc_distance(v, race.time) > race.distance {
-                1
+/* distance between the best hold and the record, kept unsigned so it never wraps */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Margin {
+    Ahead(BigUint),
+    Behind(BigUint),
+}
+
+impl std::fmt::Display for Margin {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Margin::Ahead(value) => write!(f, "{value}"),
+            Margin::Behind(value) => write!(f, "-{value}"),
+        }
+    }
+}
+
+impl Outcome {
+    /* how far the best hold goes past the record, or falls short of it */
+    fn margin(&self) -> Option<Margin> {
+        let record = BigUint::from(self.record);
+
+        self.best_distance.as_ref().map(|distance| {
+            if *distance >= record {
+                Margin::Ahead(distance - record)
             } else {
-                0
+                Margin::Behind(record - distance)
             }
         })
-        .sum::<u64>()
+    }
+}
+
+fn or_dash<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+fn or_null<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |value| value.to_string())
+}
+
+fn report_table(outcomes: &[Outcome]) -> String {
+    let mut rows = vec![[
+        "race",
+        "time",
+        "record",
+        "ways",
+        "min hold",
+        "max hold",
+        "best hold",
+        "best",
+        "margin",
+    ]
+    .map(String::from)];
+    for (idx, outcome) in outcomes.iter().enumerate() {
+        rows.push([
+            (idx + 1).to_string(),
+            outcome.time.to_string(),
+            outcome.record.to_string(),
+            outcome.ways.to_string(),
+            or_dash(outcome.winning.as_ref().map(|range| range.start())),
+            or_dash(outcome.winning.as_ref().map(|range| range.end())),
+            or_dash(outcome.best_hold),
+            or_dash(outcome.best_distance.as_ref()),
+            or_dash(outcome.margin()),
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    for row in rows.iter() {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:>width$}"))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+
+    out
+}
+
+fn report_json(outcomes: &[Outcome]) -> String {
+    let races: Vec<String> = outcomes
+        .iter()
+        .map(|outcome| {
+            format!(
+                "  {{\"time\": {}, \"record\": {}, \"ways\": {}, \"min_hold\": {}, \"max_hold\": {}, \"best_hold\": {}, \"best_distance\": {}, \"margin\": {}}}",
+                outcome.time,
+                outcome.record,
+                outcome.ways,
+                or_null(outcome.winning.as_ref().map(|range| range.start())),
+                or_null(outcome.winning.as_ref().map(|range| range.end())),
+                or_null(outcome.best_hold),
+                or_null(outcome.best_distance.as_ref()),
+                or_null(outcome.margin()),
+            )
+        })
+        .collect();
+
+    format!("[\n{}\n]\n", races.join(",\n"))
+}
+
+/* first value in low..=high for which `pred` is false, or high if it holds
+ * everywhere before it; `pred` must be true then false over the range
+ */
+fn partition_point(mut low: u64, mut high: u64, pred: impl Fn(u64) -> bool) -> u64 {
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+fn big_partition_point(low: &BigUint, high: &BigUint, pred: impl Fn(&BigUint) -> bool) -> BigUint {
+    let (mut low, mut high) = (low.clone(), high.clone());
+    while low < high {
+        let mid = &low + (&high - &low) / 2u32;
+        if pred(&mid) {
+            low = mid + 1u32;
+        } else {
+            high = mid;
+        }
+    }
+
+    low
+}
+
+#[cfg(test)]
+fn ways_brute_force(race: &Race) -> u64 {
+    (0..race.time)
+        .filter(|v| calc_distance(*v, race.time) > race.distance as u128)
+        .count() as u64
+}
+
+#[cfg(test)]
+fn part1(input: &str) -> u64 {
+    let (_, sheet) = parse_sheet(input).unwrap();
+    let races = sheet.races().expect("Invalid race sheet");
+
+    races.iter().map(Race::ways).product::<u64>()
+}
+
+fn part1_with_boat(input: &str, boat: &Boat) -> u64 {
+    let (_, sheet) = parse_sheet(input).unwrap();
+    let races = sheet.races().expect("Invalid race sheet");
+
+    races.iter().map(|race| boat.ways(race)).product::<u64>()
+}
+
+#[cfg(test)]
+fn part2(input: &str) -> Ways {
+    let (_, sheet) = parse_sheet(input).unwrap();
+    let race = sheet.kerned_any().expect("Invalid race sheet");
+
+    race.ways()
+}
+
+fn part2_with_boat(input: &str, boat: &Boat) -> Ways {
+    let (_, sheet) = parse_sheet(input).unwrap();
+    let race = sheet.kerned_any().expect("Invalid race sheet");
+
+    race.ways_with_boat(boat)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    boat: Boat,
+    sheet: bool,
+    report: Option<Report>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Report {
+    Table,
+    Json,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| panic!("Missing value for {name}"))
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("{name} must be a number"))
+        };
+        match arg.as_str() {
+            "--acceleration" => options.boat.acceleration = value("--acceleration"),
+            "--initial-speed" => options.boat.initial_speed = value("--initial-speed"),
+            "--max-speed" => options.boat.max_speed = Some(value("--max-speed")),
+            "--min-hold" => options.boat.min_hold = value("--min-hold"),
+            "--max-hold" => options.boat.max_hold = Some(value("--max-hold")),
+            "--sheet" => options.sheet = true,
+            "--report" => {
+                options.report = match args.next().as_deref() {
+                    Some("table") => Some(Report::Table),
+                    Some("json") => Some(Report::Json),
+                    _ => panic!("Report must be one of: table, json"),
+                }
+            }
+            _ => panic!("Unknown argument {arg}"),
+        }
+    }
+
+    options
 }
 
 fn main() {
     let input = include_str!("../input.txt");
 
-    let result = part1(input);
+    let options = parse_args(std::env::args().skip(1));
+
+    if options.sheet {
+        let (_, sheet) = parse_sheet(input).expect("Unable to parse input");
+        let races = sheet.races().expect("Invalid race sheet");
+        for (idx, race) in races.iter().enumerate() {
+            print!(
+                "Race {}: {} ms, record {} mm",
+                idx + 1,
+                race.time,
+                race.distance
+            );
+            for row in sheet.extra_rows() {
+                print!(", {} {}", row.label, row.columns[idx]);
+            }
+            println!();
+        }
+        let race = sheet.kerned_any().expect("Invalid race sheet");
+        println!("Kerned: {race}");
+        return;
+    }
+
+    if let Some(report) = &options.report {
+        let (_, sheet) = parse_sheet(input).expect("Unable to parse input");
+        let outcomes: Vec<_> = sheet
+            .races()
+            .expect("Invalid race sheet")
+            .iter()
+            .map(|race| options.boat.outcome(race))
+            .collect();
+        match report {
+            Report::Table => print!("{}", report_table(&outcomes)),
+            Report::Json => print!("{}", report_json(&outcomes)),
+        }
+        return;
+    }
+
+    let result = part1_with_boat(input, &options.boat);
     println!("Result part 1: {result}");
-    let result = part2(input);
+    let result = part2_with_boat(input, &options.boat);
     println!("Result part 2: {result}");
 }
 
@@ -133,3 +857,451 @@ Distance:  9  40  200
 ";
     assert_eq!(part2(input), 71503);
 }
+
+#[test]
+fn test_isqrt() {
+    for n in 0..10_000u128 {
+        let r = isqrt(n);
+        assert!(r * r <= n && (r + 1) * (r + 1) > n, "isqrt({n}) = {r}");
+    }
+    assert_eq!(isqrt(u64::MAX as u128 * u64::MAX as u128), u64::MAX as u128);
+    assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    assert_eq!(isqrt((1u128 << 100) - 1), (1u128 << 50) - 1);
+}
+
+#[test]
+fn test_winning() {
+    let race = Race {
+        time: 7,
+        distance: 9,
+    };
+    assert_eq!(race.winning(), Some(2..=5));
+
+    /* 10 * 20 equals the record, it does not beat it */
+    let race = Race {
+        time: 30,
+        distance: 200,
+    };
+    assert_eq!(race.winning(), Some(11..=19));
+
+    let race = Race {
+        time: 10,
+        distance: 25,
+    };
+    assert_eq!(race.winning(), None);
+    assert_eq!(race.ways(), 0);
+
+    let race = Race {
+        time: 71530,
+        distance: 940200,
+    };
+    assert_eq!(race.winning(), Some(14..=71516));
+
+    for time in 0..60 {
+        for distance in 0..(time * time / 4 + 3) {
+            let race = Race { time, distance };
+            assert_eq!(race.ways(), ways_brute_force(&race), "{race:?}");
+        }
+    }
+
+    /* no overflow for times near the top of u64 */
+    let race = Race {
+        time: u64::MAX,
+        distance: u64::MAX,
+    };
+    assert_eq!(race.winning(), Some(2..=u64::MAX - 2));
+}
+
+#[test]
+fn test_boat() {
+    fn brute_force(boat: &Boat, race: &Race) -> u64 {
+        boat.holds(race.time).map_or(0, |holds| {
+            holds
+                .filter(|hold| {
+                    boat.distance(*hold, race.time)
+                        .is_some_and(|distance| distance > race.distance as u128)
+                })
+                .count() as u64
+        })
+    }
+
+    let boats = [
+        Boat::default(),
+        Boat {
+            acceleration: 3,
+            ..Boat::default()
+        },
+        Boat {
+            initial_speed: 4,
+            ..Boat::default()
+        },
+        Boat {
+            max_speed: Some(5),
+            ..Boat::default()
+        },
+        Boat {
+            acceleration: 2,
+            initial_speed: 1,
+            max_speed: Some(9),
+            min_hold: 3,
+            max_hold: Some(12),
+        },
+        Boat {
+            acceleration: 0,
+            initial_speed: 2,
+            ..Boat::default()
+        },
+        Boat {
+            min_hold: 8,
+            max_hold: Some(4),
+            ..Boat::default()
+        },
+    ];
+    for boat in boats.iter() {
+        for time in 0..40 {
+            for distance in 0..200 {
+                let race = Race { time, distance };
+                assert_eq!(
+                    boat.ways(&race),
+                    brute_force(boat, &race),
+                    "{boat:?} {race:?}"
+                );
+            }
+        }
+    }
+
+    let input = "Time:      7  15   30
+Distance:  9  40  200
+";
+    assert_eq!(part1_with_boat(input, &Boat::default()), 288);
+    assert_eq!(part2_with_boat(input, &Boat::default()), 71503);
+
+    /* capped at 5 mm/ms the 30 ms race can reach at most 5 * 25 = 125 */
+    let boat = Boat {
+        max_speed: Some(5),
+        ..Boat::default()
+    };
+    let race = Race {
+        time: 30,
+        distance: 100,
+    };
+    assert_eq!(boat.winning(&race), Some(4..=9));
+    assert_eq!(part1_with_boat(input, &boat), 0);
+
+    let options = parse_args(
+        [
+            "--acceleration",
+            "2",
+            "--max-speed",
+            "9",
+            "--max-hold",
+            "12",
+        ]
+        .into_iter()
+        .map(String::from),
+    );
+    assert_eq!(
+        options.boat,
+        Boat {
+            acceleration: 2,
+            max_speed: Some(9),
+            max_hold: Some(12),
+            ..Boat::default()
+        }
+    );
+}
+
+#[test]
+fn test_sheet() {
+    let input = "Time:      7  15   30
+Distance:  9  40  200
+Record holder:  ann bob cid";
+    let (rest, sheet) = parse_sheet(input).unwrap();
+    assert_eq!(rest, "");
+    assert_eq!(
+        sheet.row("Time"),
+        Some(&Row {
+            label: "Time",
+            columns: vec!["7", "15", "30"],
+        })
+    );
+    let extra: Vec<_> = sheet.extra_rows().map(|row| row.label).collect();
+    assert_eq!(extra, vec!["Record holder"]);
+
+    let races = sheet.races().unwrap();
+    assert_eq!(races.len(), 3);
+    assert_eq!((races[2].time, races[2].distance), (30, 200));
+    let race = sheet.kerned().unwrap();
+    assert_eq!((race.time, race.distance), (71530, 940200));
+
+    /* extra rows still have to line up with the races */
+    let (_, sheet) = parse_sheet("Time: 7 15 30\nDistance: 9 40 200\nHolder: ann bob\n").unwrap();
+    assert_eq!(
+        sheet.races().unwrap_err(),
+        SheetError::ColumnCount {
+            label: "Holder".to_string(),
+            expected: 3,
+            found: 2,
+        }
+    );
+    assert_eq!(
+        sheet.kerned().unwrap_err().to_string(),
+        "Holder row has 2 columns, expected 3"
+    );
+
+    let (_, sheet) = parse_sheet("Time: 7 15\nDistance: 9 4o\n").unwrap();
+    assert_eq!(
+        sheet.races().unwrap_err(),
+        SheetError::NotANumber {
+            label: "Distance".to_string(),
+            value: "4o".to_string(),
+        }
+    );
+
+    let (_, sheet) = parse_sheet("Time: 7 15\n").unwrap();
+    assert_eq!(
+        sheet.races().unwrap_err(),
+        SheetError::MissingRow("Distance")
+    );
+}
+
+#[test]
+fn test_outcomes() {
+    let input = "Time:      7  15   30
+Distance:  9  40  200
+";
+    let (_, sheet) = parse_sheet(input).unwrap();
+    let outcomes: Vec<_> = sheet
+        .races()
+        .unwrap()
+        .iter()
+        .map(|race| Boat::default().outcome(race))
+        .collect();
+
+    assert_eq!(
+        outcomes[0],
+        Outcome {
+            time: 7,
+            record: 9,
+            ways: 4,
+            winning: Some(2..=5),
+            best_hold: Some(3),
+            best_distance: Some(BigUint::from(12u32)),
+        }
+    );
+    assert_eq!(
+        outcomes[0].margin(),
+        Some(Margin::Ahead(BigUint::from(3u32)))
+    );
+    assert_eq!(outcomes[2].best_hold, Some(15));
+    assert_eq!(
+        outcomes[2].margin(),
+        Some(Margin::Ahead(BigUint::from(25u32)))
+    );
+
+    /* the generic peak search agrees with T / 2 */
+    let boat = Boat {
+        max_hold: Some(u64::MAX),
+        ..Boat::default()
+    };
+    for race in sheet.races().unwrap().iter() {
+        let (outcome, generic) = (Boat::default().outcome(race), boat.outcome(race));
+        assert_eq!(outcome.best_distance, generic.best_distance);
+        assert_eq!(outcome.winning, generic.winning);
+    }
+
+    /* a slow boat never wins, the margin shows by how much it misses */
+    let boat = Boat {
+        max_speed: Some(5),
+        ..Boat::default()
+    };
+    let outcome = boat.outcome(&Race {
+        time: 30,
+        distance: 200,
+    });
+    assert_eq!(outcome.ways, 0);
+    assert_eq!(outcome.winning, None);
+    assert_eq!(outcome.best_hold, Some(5));
+    assert_eq!(outcome.margin(), Some(Margin::Behind(BigUint::from(75u32))));
+
+    assert_eq!(
+        report_table(&outcomes),
+        "race  time  record  ways  min hold  max hold  best hold  best  margin
+   1     7       9     4         2         5          3    12       3
+   2    15      40     8         4        11          7    56      16
+   3    30     200     9        11        19         15   225      25
+"
+    );
+    assert_eq!(
+        report_json(&outcomes[..1]),
+        "[
+  {\"time\": 7, \"record\": 9, \"ways\": 4, \"min_hold\": 2, \"max_hold\": 5, \"best_hold\": 3, \"best_distance\": 12, \"margin\": 3}
+]
+"
+    );
+    let json = report_json(&[outcome]);
+    assert!(json.contains("\"min_hold\": null"));
+    assert!(json.contains("\"margin\": -75"));
+
+    /* 1000 * (T / 2)^2 is past both u128 and i128 */
+    let boat = Boat {
+        acceleration: 1000,
+        ..Boat::default()
+    };
+    let outcome = boat.outcome(&Race {
+        time: 1 << 62,
+        distance: 7,
+    });
+    let best = BigUint::from(1000u32) * (BigUint::from(1u32) << 122u32);
+    assert_eq!(outcome.best_hold, Some(1 << 61));
+    assert_eq!(outcome.best_distance, Some(best.clone()));
+    assert_eq!(outcome.margin(), Some(Margin::Ahead(best - 7u32)));
+    assert_eq!(outcome.winning, Some(1..=(1 << 62) - 1));
+}
+
+#[test]
+fn test_kerned_overflow() {
+    /* 18446744073709551615 is u64::MAX, one more digit group pushes past it */
+    let input = "Time:      184467440 737095516 15 00
+Distance:  9  40  200 0
+";
+    let (_, sheet) = parse_sheet(input).unwrap();
+    assert_eq!(
+        sheet.kerned().unwrap_err(),
+        SheetError::TooLarge {
+            label: "Time".to_string(),
+            value: "1844674407370955161500".to_string(),
+        }
+    );
+
+    let Ok(KernedRace::Big(race)) = sheet.kerned_any() else {
+        panic!("expected a big race");
+    };
+    assert_eq!(race.time, BigUint::from(u64::MAX) * 100u32);
+    assert_eq!(race.distance, BigUint::from(9402000u32));
+
+    /* a u64 time wins on the same holds either way */
+    let (_, sheet) = parse_sheet("Time: 71 530\nDistance: 940 200\n").unwrap();
+    let race = sheet.kerned().unwrap();
+    let big = BigRace {
+        time: BigUint::from(race.time),
+        distance: BigUint::from(race.distance),
+    };
+    assert_eq!(big.ways(), BigUint::from(race.ways()));
+    assert_eq!(
+        big.winning(),
+        Some(BigUint::from(14u32)..=BigUint::from(71516u32))
+    );
+    assert_eq!(part2("Time: 71 530\nDistance: 940 200\n"), 71503);
+
+    for time in 0..40u32 {
+        for distance in 0..(time * time / 4 + 3) {
+            let race = Race {
+                time: time as u64,
+                distance: distance as u64,
+            };
+            let big = BigRace {
+                time: BigUint::from(time),
+                distance: BigUint::from(distance),
+            };
+            assert_eq!(big.ways(), BigUint::from(race.ways()), "{race:?}");
+        }
+    }
+
+    /* 41 digit time with an 80 digit record: t * (T - t) > D around T / 2 */
+    let time = BigUint::from(10u32).pow(40);
+    let distance = BigUint::from(10u32).pow(79) * 2u32;
+    let race = BigRace {
+        time: time.clone(),
+        distance: distance.clone(),
+    };
+    let range = race.winning().unwrap();
+    let (first, last) = (range.start().clone(), range.end().clone());
+    assert!(&first * (&time - &first) > distance);
+    assert!((&first - 1u32) * (&time - &first + 1u32) <= distance);
+    assert_eq!(&first + &last, time);
+
+    let input = format!("Time: {time}\nDistance: {distance}\n");
+    match part2(&input) {
+        Ways::Big(ways) => assert_eq!(ways, last - first + 1u32),
+        ways => panic!("expected a big count, got {ways}"),
+    }
+}
+
+#[test]
+fn test_kerned_overflow_with_boat() {
+    let boats = [
+        Boat {
+            acceleration: 3,
+            ..Boat::default()
+        },
+        Boat {
+            acceleration: 2,
+            initial_speed: 1,
+            max_speed: Some(9),
+            min_hold: 3,
+            max_hold: Some(12),
+        },
+        Boat {
+            min_hold: 8,
+            max_hold: Some(4),
+            ..Boat::default()
+        },
+    ];
+    for boat in boats.iter() {
+        for time in 0..30u64 {
+            for distance in 0..100u64 {
+                let race = Race { time, distance };
+                let big = BigRace {
+                    time: BigUint::from(time),
+                    distance: BigUint::from(distance),
+                };
+                assert_eq!(
+                    boat.big_ways(&big),
+                    BigUint::from(boat.ways(&race)),
+                    "{boat:?} {race:?}"
+                );
+            }
+        }
+    }
+
+    /* capped at 5 mm/ms, only holds of 5 to 99 ms get past 5 * (T - 100) */
+    let time = BigUint::from(10u32).pow(30);
+    let distance = (&time - 100u32) * 5u32;
+    let boat = Boat {
+        max_speed: Some(5),
+        ..Boat::default()
+    };
+    let race = BigRace {
+        time: time.clone(),
+        distance: distance.clone(),
+    };
+    assert_eq!(
+        boat.big_winning(&race),
+        Some(BigUint::from(5u32)..=BigUint::from(99u32))
+    );
+
+    let input = format!("Time: {time}\nDistance: {distance}\n");
+    assert_eq!(part2_with_boat(&input, &boat), 95);
+    assert_eq!(part2_with_boat(&input, &Boat::default()), part2(&input));
+}
+
+#[test]
+fn test_boat_distance_past_u128() {
+    /* 100000 * T * T / 4 does not fit in a u128 although T fits in a u64 */
+    let input = "Time: 1000000000000000000\nDistance: 5\n";
+    let boat = Boat {
+        acceleration: 100000,
+        ..Boat::default()
+    };
+    let race = Race {
+        time: 1000000000000000000,
+        distance: 5,
+    };
+    assert!(!boat.fits(race.time));
+    assert_eq!(boat.distance(race.time / 2, race.time), None);
+    assert_eq!(boat.winning(&race), Some(1..=999999999999999999));
+    assert_eq!(boat.peak(&race), Some(500000000000000000));
+    assert_eq!(part2_with_boat(input, &boat), 999999999999999999);
+    assert_eq!(part1_with_boat(input, &boat), 999999999999999999);
+}